@@ -0,0 +1,70 @@
+extern crate sapling_crypto;
+extern crate bellman;
+extern crate rand;
+extern crate pairing;
+
+use std::time::{Duration, Instant};
+use sapling_crypto::jubjub::{
+    JubjubBls12,
+    fs
+};
+use sapling_crypto::keys::{
+    ExpandedSpendingKey,
+    FullViewingKey
+};
+use sapling_crypto::merkle_tree::{
+    CommitmentTree,
+    IncrementalWitness
+};
+use sapling_crypto::prover::{
+    create_spend_proof,
+    spend_parameters
+};
+use rand::{XorShiftRng, SeedableRng, Rng};
+use pairing::bls12_381::Bls12;
+
+fn main() {
+    let jubjub_params = &JubjubBls12::new();
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    println!("Creating sample parameters...");
+    let groth_params = spend_parameters::<Bls12, _>(jubjub_params, rng).unwrap();
+
+    let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[0u8; 32]);
+    let proof_generation_key = expsk.proof_generation_key(jubjub_params);
+    let fvk = FullViewingKey::from_expanded_spending_key(&expsk, jubjub_params);
+    let (_, payment_address) = fvk.default_address(jubjub_params).unwrap();
+
+    const SAMPLES: u32 = 50;
+
+    let mut total_time = Duration::new(0, 0);
+    for _ in 0..SAMPLES {
+        let value: u64 = 1;
+        let value_randomness: fs::Fs = rng.gen();
+        let commitment_randomness: fs::Fs = rng.gen();
+        let note = payment_address.create_note(value, commitment_randomness, jubjub_params).unwrap();
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        tree.append(note.cm(jubjub_params), jubjub_params).unwrap();
+        let witness = IncrementalWitness::from_tree(&tree);
+        let path = witness.path(jubjub_params).unwrap();
+
+        let start = Instant::now();
+        let _ = create_spend_proof(
+            &note,
+            payment_address.diversifier,
+            &proof_generation_key,
+            &path,
+            value_randomness,
+            &groth_params,
+            jubjub_params,
+            rng
+        ).unwrap();
+        total_time += start.elapsed();
+    }
+    let avg = total_time / SAMPLES;
+    let avg = avg.subsec_nanos() as f64 / 1_000_000_000f64
+              + (avg.as_secs() as f64);
+
+    println!("Average proving time (in seconds): {}", avg);
+}
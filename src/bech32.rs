@@ -0,0 +1,423 @@
+//! Bech32 encoding of addresses and keys, as specified in BIP 173
+//! but without its limit of 90 characters, which our keys exceed.
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use jubjub::JubjubEngine;
+
+use keys::{
+    ExtendedSpendingKey,
+    EXTENDED_KEY_SIZE
+};
+
+use primitives::{
+    IncomingViewingKey,
+    PaymentAddress,
+    ViewingKey
+};
+
+/// Human-readable prefix of mainnet payment addresses
+pub const MAINNET_PAYMENT_ADDRESS_HRP: &'static str = "zs";
+/// Human-readable prefix of testnet payment addresses
+pub const TESTNET_PAYMENT_ADDRESS_HRP: &'static str = "ztestsapling";
+/// Human-readable prefix of mainnet viewing keys
+pub const MAINNET_VIEWING_KEY_HRP: &'static str = "zviews";
+/// Human-readable prefix of testnet viewing keys
+pub const TESTNET_VIEWING_KEY_HRP: &'static str = "zviewtestsapling";
+/// Human-readable prefix of mainnet incoming viewing keys
+pub const MAINNET_INCOMING_VIEWING_KEY_HRP: &'static str = "zivks";
+/// Human-readable prefix of testnet incoming viewing keys
+pub const TESTNET_INCOMING_VIEWING_KEY_HRP: &'static str = "zivktestsapling";
+/// Human-readable prefix of mainnet extended spending keys
+pub const MAINNET_EXTENDED_SPENDING_KEY_HRP: &'static str = "secret-extended-key-main";
+/// Human-readable prefix of testnet extended spending keys
+pub const TESTNET_EXTENDED_SPENDING_KEY_HRP: &'static str = "secret-extended-key-test";
+
+const CHARSET: &'static [u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+const CHECKSUM_LENGTH: usize = 6;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The string has no `1` separating the prefix from the data
+    MissingSeparator,
+    /// The prefix is empty or contains characters outside of 33 to 126
+    InvalidHrp,
+    /// The prefix differs from the one expected for the decoded type
+    WrongHrp,
+    /// The data contains a character outside of the Bech32 alphabet
+    InvalidChar(char),
+    /// The string mixes upper and lower case characters
+    MixedCase,
+    /// The data is too short to contain a checksum
+    InvalidLength,
+    /// The checksum does not match the prefix and data
+    InvalidChecksum,
+    /// The data does not pack into whole bytes
+    InvalidPadding,
+    /// The bytes are not a valid encoding of the decoded type
+    InvalidPayload
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::MissingSeparator => write!(f, "missing separator"),
+            Error::InvalidHrp => write!(f, "invalid human-readable prefix"),
+            Error::WrongHrp => write!(f, "unexpected human-readable prefix"),
+            Error::InvalidChar(c) => write!(f, "invalid character {:?}", c),
+            Error::MixedCase => write!(f, "mixed case"),
+            Error::InvalidLength => write!(f, "invalid length"),
+            Error::InvalidChecksum => write!(f, "invalid checksum"),
+            Error::InvalidPadding => write!(f, "invalid padding"),
+            Error::InvalidPayload => write!(f, "invalid payload")
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk = 1u32;
+    for v in values {
+        let b = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (*v as u32);
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= *g;
+            }
+        }
+    }
+
+    chk
+}
+
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+    let mut v = Vec::with_capacity(hrp.len() * 2 + 1);
+    v.extend(hrp.iter().map(|c| c >> 5));
+    v.push(0);
+    v.extend(hrp.iter().map(|c| c & 0x1f));
+
+    v
+}
+
+fn check_hrp(hrp: &str) -> Result<(), Error> {
+    if hrp.is_empty() {
+        return Err(Error::InvalidHrp);
+    }
+
+    let mut has_lower = false;
+    let mut has_upper = false;
+    for c in hrp.bytes() {
+        if c < 33 || c > 126 {
+            return Err(Error::InvalidHrp);
+        }
+        has_lower |= c >= b'a' && c <= b'z';
+        has_upper |= c >= b'A' && c <= b'Z';
+    }
+
+    if has_lower && has_upper {
+        return Err(Error::MixedCase);
+    }
+
+    Ok(())
+}
+
+/// Regroups the bits of `data` from `from`-bit to `to`-bit values.
+/// Without `pad`, the remaining bits must be fewer than `from` and zero.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let maxv = (1u32 << to) - 1;
+    let mut ret = Vec::with_capacity((data.len() * from as usize + to as usize - 1) / to as usize);
+
+    for value in data {
+        acc = (acc << from) | (*value as u32);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            ret.push(((acc >> bits) & maxv) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            ret.push(((acc << (to - bits)) & maxv) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & maxv) != 0 {
+        return Err(Error::InvalidPadding);
+    }
+
+    Ok(ret)
+}
+
+/// Encodes `data` under the human-readable prefix `hrp`, which is
+/// normalized to lower case.
+pub fn encode(hrp: &str, data: &[u8]) -> Result<String, Error> {
+    check_hrp(hrp)?;
+    let hrp = hrp.to_lowercase();
+
+    let data = convert_bits(data, 8, 5, true)?;
+
+    let mut values = hrp_expand(hrp.as_bytes());
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+    let checksum = polymod(&values) ^ 1;
+
+    let mut s = hrp;
+    s.push('1');
+    for v in data {
+        s.push(CHARSET[v as usize] as char);
+    }
+    for i in 0..CHECKSUM_LENGTH {
+        let v = (checksum >> (5 * (5 - i))) & 0x1f;
+        s.push(CHARSET[v as usize] as char);
+    }
+
+    Ok(s)
+}
+
+/// Decodes a Bech32 string, returning its human-readable prefix in
+/// lower case and its data.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), Error> {
+    let mut has_lower = false;
+    let mut has_upper = false;
+    for c in s.chars() {
+        has_lower |= c.is_lowercase();
+        has_upper |= c.is_uppercase();
+    }
+    if has_lower && has_upper {
+        return Err(Error::MixedCase);
+    }
+    let s = s.to_lowercase();
+
+    let pos = match s.rfind('1') {
+        Some(pos) => pos,
+        None => return Err(Error::MissingSeparator)
+    };
+    let (hrp, data) = s.split_at(pos);
+    let data = &data[1..];
+
+    check_hrp(hrp)?;
+    if data.len() < CHECKSUM_LENGTH {
+        return Err(Error::InvalidLength);
+    }
+
+    let mut values = Vec::with_capacity(data.len());
+    for c in data.chars() {
+        match CHARSET.iter().position(|x| *x as char == c) {
+            Some(v) => values.push(v as u8),
+            None => return Err(Error::InvalidChar(c))
+        }
+    }
+
+    let mut checked = hrp_expand(hrp.as_bytes());
+    checked.extend_from_slice(&values);
+    if polymod(&checked) != 1 {
+        return Err(Error::InvalidChecksum);
+    }
+
+    let data_len = values.len() - CHECKSUM_LENGTH;
+    let data = convert_bits(&values[..data_len], 5, 8, false)?;
+
+    Ok((hrp.to_string(), data))
+}
+
+fn encode_with<F>(hrp: &str, write: F) -> Result<String, Error>
+    where F: FnOnce(&mut Vec<u8>) -> io::Result<()>
+{
+    let mut data = vec![];
+    write(&mut data).expect("should be able to write to a Vec");
+
+    encode(hrp, &data)
+}
+
+fn decode_with<T, X, F>(hrp: &str, s: &str, len: usize, read: F) -> Result<T, Error>
+    where F: FnOnce(&[u8]) -> Result<T, X>
+{
+    let (found, data) = decode(s)?;
+    if found != hrp.to_lowercase() {
+        return Err(Error::WrongHrp);
+    }
+
+    if data.len() != len {
+        return Err(Error::InvalidPayload);
+    }
+
+    read(&data).map_err(|_| Error::InvalidPayload)
+}
+
+pub fn encode_payment_address<E: JubjubEngine>(
+    hrp: &str,
+    addr: &PaymentAddress<E>
+) -> Result<String, Error>
+{
+    encode_with(hrp, |data| addr.write(data))
+}
+
+pub fn decode_payment_address<E: JubjubEngine>(
+    hrp: &str,
+    s: &str,
+    params: &E::Params
+) -> Result<PaymentAddress<E>, Error>
+{
+    decode_with(hrp, s, 43, |data| PaymentAddress::read(data, params))
+}
+
+pub fn encode_viewing_key<E: JubjubEngine>(
+    hrp: &str,
+    vk: &ViewingKey<E>
+) -> Result<String, Error>
+{
+    encode_with(hrp, |data| vk.write(data))
+}
+
+pub fn decode_viewing_key<E: JubjubEngine>(
+    hrp: &str,
+    s: &str,
+    params: &E::Params
+) -> Result<ViewingKey<E>, Error>
+{
+    decode_with(hrp, s, 64, |data| ViewingKey::read(data, params))
+}
+
+pub fn encode_incoming_viewing_key<E: JubjubEngine>(
+    hrp: &str,
+    ivk: &IncomingViewingKey<E>
+) -> Result<String, Error>
+{
+    encode_with(hrp, |data| ivk.write(data))
+}
+
+pub fn decode_incoming_viewing_key<E: JubjubEngine>(
+    hrp: &str,
+    s: &str
+) -> Result<IncomingViewingKey<E>, Error>
+{
+    decode_with(hrp, s, 32, |data| IncomingViewingKey::read(data))
+}
+
+pub fn encode_extended_spending_key<E: JubjubEngine>(
+    hrp: &str,
+    xsk: &ExtendedSpendingKey<E>
+) -> Result<String, Error>
+{
+    encode_with(hrp, |data| xsk.write(data))
+}
+
+pub fn decode_extended_spending_key<E: JubjubEngine>(
+    hrp: &str,
+    s: &str
+) -> Result<ExtendedSpendingKey<E>, Error>
+{
+    decode_with(hrp, s, EXTENDED_KEY_SIZE, |data| ExtendedSpendingKey::read(data))
+}
+
+#[cfg(test)]
+mod test {
+    use pairing::bls12_381::Bls12;
+    use jubjub::JubjubBls12;
+    use keys::{ExtendedSpendingKey, FullViewingKey};
+    use super::*;
+
+    #[test]
+    fn test_bip173_vectors() {
+        let valid = [
+            "A12UEL5L",
+            "a12uel5l",
+            "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+            "abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+            "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+            "?1ezyfcl"
+        ];
+
+        for s in valid.iter() {
+            let (hrp, data) = decode(s).unwrap();
+            assert_eq!(encode(&hrp, &data).unwrap(), s.to_lowercase());
+        }
+
+        assert_eq!(decode("pzry9x0s0muk"), Err(Error::MissingSeparator));
+        assert_eq!(decode("1pzry9x0s0muk"), Err(Error::InvalidHrp));
+        assert_eq!(decode("x1b4n0q5v"), Err(Error::InvalidChar('b')));
+        assert_eq!(decode("li1dgmt3"), Err(Error::InvalidLength));
+        assert_eq!(decode("A1G7SGD8"), Err(Error::InvalidChecksum));
+        assert_eq!(decode("10a06t8"), Err(Error::InvalidHrp));
+        assert_eq!(decode("1qzzfhee"), Err(Error::InvalidHrp));
+        assert_eq!(decode("a12UEL5L"), Err(Error::MixedCase));
+        assert_eq!(decode("\x201nwldj5"), Err(Error::InvalidHrp));
+    }
+
+    #[test]
+    fn test_padding() {
+        // A single zero byte needs two 5-bit groups.
+        let s = encode("a", &[0]).unwrap();
+        assert_eq!(decode(&s).unwrap().1, vec![0]);
+
+        // Three 5-bit groups leave 7 bits over, more than a padding.
+        let mut values = hrp_expand(b"a");
+        values.extend_from_slice(&[0, 0, 0]);
+        values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+        let checksum = polymod(&values) ^ 1;
+        let mut s = String::from("a1qqq");
+        for i in 0..CHECKSUM_LENGTH {
+            s.push(CHARSET[((checksum >> (5 * (5 - i))) & 0x1f) as usize] as char);
+        }
+        assert_eq!(decode(&s), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn test_keys_and_addresses() {
+        let params = &JubjubBls12::new();
+
+        let xsk = ExtendedSpendingKey::<Bls12>::master(&[3u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&xsk.expsk, params);
+        let ivk = fvk.vk.ivk();
+        let (_, addr) = fvk.default_address(params).unwrap();
+
+        let s = encode_payment_address(MAINNET_PAYMENT_ADDRESS_HRP, &addr).unwrap();
+        assert!(s.starts_with("zs1"));
+        let addr_2 = decode_payment_address::<Bls12>(MAINNET_PAYMENT_ADDRESS_HRP, &s, params).unwrap();
+        assert!(addr_2.pk_d == addr.pk_d);
+        assert_eq!(
+            decode_payment_address::<Bls12>(TESTNET_PAYMENT_ADDRESS_HRP, &s, params).err(),
+            Some(Error::WrongHrp)
+        );
+
+        // Upper case strings decode as well.
+        let addr_2 = decode_payment_address::<Bls12>(MAINNET_PAYMENT_ADDRESS_HRP, &s.to_uppercase(), params).unwrap();
+        assert!(addr_2.pk_d == addr.pk_d);
+
+        // Single character substitutions are detected.
+        let mut corrupted = s.clone().into_bytes();
+        corrupted[10] = if corrupted[10] == b'q' { b'p' } else { b'q' };
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert_eq!(
+            decode_payment_address::<Bls12>(MAINNET_PAYMENT_ADDRESS_HRP, &corrupted, params).err(),
+            Some(Error::InvalidChecksum)
+        );
+
+        let s = encode_viewing_key(TESTNET_VIEWING_KEY_HRP, &fvk.vk).unwrap();
+        let vk = decode_viewing_key::<Bls12>(TESTNET_VIEWING_KEY_HRP, &s, params).unwrap();
+        assert!(vk.ak == fvk.vk.ak);
+        assert!(vk.rk == fvk.vk.rk);
+
+        let s = encode_incoming_viewing_key(MAINNET_INCOMING_VIEWING_KEY_HRP, &ivk).unwrap();
+        assert!(decode_incoming_viewing_key::<Bls12>(MAINNET_INCOMING_VIEWING_KEY_HRP, &s).unwrap() == ivk);
+
+        let s = encode_extended_spending_key(MAINNET_EXTENDED_SPENDING_KEY_HRP, &xsk).unwrap();
+        assert!(s.len() > 90);
+        let xsk_2 = decode_extended_spending_key::<Bls12>(MAINNET_EXTENDED_SPENDING_KEY_HRP, &s).unwrap();
+        assert_eq!(xsk_2.expsk.ask, xsk.expsk.ask);
+
+        // Payloads of the wrong type are rejected.
+        let s = encode(MAINNET_PAYMENT_ADDRESS_HRP, &[0u8; 42]).unwrap();
+        assert_eq!(
+            decode_payment_address::<Bls12>(MAINNET_PAYMENT_ADDRESS_HRP, &s, params).err(),
+            Some(Error::InvalidPayload)
+        );
+    }
+}
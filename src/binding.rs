@@ -0,0 +1,276 @@
+//! Binding signatures prove that the value commitments of a transaction's
+//! spends and outputs balance against its public value balance.
+//!
+//! The value commitments `cv = [v] G_v + [rcv] G_r` are homomorphic, so
+//! the sum of the spend commitments minus the sum of the output
+//! commitments, less `[value_balance] G_v`, is `[bsk] G_r` where `bsk` is
+//! the corresponding sum of commitment randomness. Knowledge of `bsk` is
+//! demonstrated with a RedJubjub signature over the `G_r` base, which is
+//! only possible when the values actually balance.
+
+use pairing::Field;
+
+use rand::Rng;
+
+use jubjub::{
+    edwards,
+    FixedGenerators,
+    JubjubEngine,
+    PrimeOrder,
+    Unknown
+};
+
+use primitives::ValueCommitment;
+
+use util::zeroize;
+
+use redjubjub::{
+    PrivateKey,
+    PublicKey,
+    Signature
+};
+
+/// The base over which binding signature keys are defined.
+pub const BINDING_SIGNATURE_GENERATOR: FixedGenerators = FixedGenerators::ValueCommitmentRandomness;
+
+/// Computes `[value_balance] G_v` for a signed value balance.
+pub fn value_balance_point<E: JubjubEngine>(
+    value_balance: i64,
+    params: &E::Params
+) -> edwards::Point<E, PrimeOrder>
+{
+    // This is the magnitude even for i64::min_value()
+    let magnitude = if value_balance < 0 {
+        value_balance.wrapping_neg() as u64
+    } else {
+        value_balance as u64
+    };
+
+    let p = edwards::Point::fixed_base_mul(FixedGenerators::ValueCommitmentValue, magnitude, params);
+
+    if value_balance < 0 {
+        p.negate()
+    } else {
+        p
+    }
+}
+
+/// Computes the binding verification key from the public value
+/// commitments of the spends and outputs and the value balance.
+pub fn verification_key<E: JubjubEngine>(
+    spend_cvs: &[edwards::Point<E, Unknown>],
+    output_cvs: &[edwards::Point<E, Unknown>],
+    value_balance: i64,
+    params: &E::Params
+) -> PublicKey<E>
+{
+    let mut bvk = edwards::Point::zero();
+
+    for cv in spend_cvs {
+        bvk = bvk.add(cv, params);
+    }
+
+    for cv in output_cvs {
+        bvk = bvk.add(&cv.negate(), params);
+    }
+
+    let balance: edwards::Point<E, Unknown> = value_balance_point(value_balance, params).into();
+    bvk = bvk.add(&balance.negate(), params);
+
+    PublicKey(bvk)
+}
+
+/// Verifies a binding signature over `sighash` against the public value
+/// commitments and value balance of a transaction.
+pub fn verify<E: JubjubEngine>(
+    spend_cvs: &[edwards::Point<E, Unknown>],
+    output_cvs: &[edwards::Point<E, Unknown>],
+    value_balance: i64,
+    sighash: &[u8],
+    sig: &Signature,
+    params: &E::Params
+) -> bool
+{
+    verification_key(spend_cvs, output_cvs, value_balance, params)
+        .verify(sighash, sig, BINDING_SIGNATURE_GENERATOR, params)
+}
+
+/// Accumulates the value commitments of a transaction's spends and
+/// outputs in order to produce its binding signature.
+pub struct BindingContext<E: JubjubEngine> {
+    bsk: E::Fs,
+    cv_sum: edwards::Point<E, PrimeOrder>,
+    value_balance: i64
+}
+
+impl<E: JubjubEngine> Drop for BindingContext<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.bsk, E::Fs::zero());
+    }
+}
+
+impl<E: JubjubEngine> BindingContext<E> {
+    pub fn new() -> Self {
+        BindingContext {
+            bsk: E::Fs::zero(),
+            cv_sum: edwards::Point::zero(),
+            value_balance: 0
+        }
+    }
+
+    /// Accounts for the value commitment of a spend. Returns an error
+    /// if the value balance would overflow.
+    pub fn add_spend(
+        &mut self,
+        value_commitment: &ValueCommitment<E>,
+        params: &E::Params
+    ) -> Result<(), ()>
+    {
+        if value_commitment.value > i64::max_value() as u64 {
+            return Err(());
+        }
+
+        self.value_balance = self.value_balance
+                                 .checked_add(value_commitment.value as i64)
+                                 .ok_or(())?;
+        self.bsk.add_assign(&value_commitment.randomness);
+        self.cv_sum = self.cv_sum.add(&value_commitment.cm(params), params);
+
+        Ok(())
+    }
+
+    /// Accounts for the value commitment of an output. Returns an error
+    /// if the value balance would overflow.
+    pub fn add_output(
+        &mut self,
+        value_commitment: &ValueCommitment<E>,
+        params: &E::Params
+    ) -> Result<(), ()>
+    {
+        if value_commitment.value > i64::max_value() as u64 {
+            return Err(());
+        }
+
+        self.value_balance = self.value_balance
+                                 .checked_sub(value_commitment.value as i64)
+                                 .ok_or(())?;
+        self.bsk.sub_assign(&value_commitment.randomness);
+        self.cv_sum = self.cv_sum.add(&value_commitment.cm(params).negate(), params);
+
+        Ok(())
+    }
+
+    /// The net value of the spends less the outputs, which is
+    /// published with the transaction.
+    pub fn value_balance(&self) -> i64 {
+        self.value_balance
+    }
+
+    /// The sum of the value commitments of the spends less the
+    /// value commitments of the outputs.
+    pub fn cv_sum(&self) -> &edwards::Point<E, PrimeOrder> {
+        &self.cv_sum
+    }
+
+    /// The net commitment randomness, as a binding signing key.
+    pub fn signing_key(&self) -> PrivateKey<E> {
+        PrivateKey(self.bsk)
+    }
+
+    /// The verification key corresponding to `signing_key`, computed
+    /// from the commitments and value balance as a verifier would.
+    pub fn verification_key(&self, params: &E::Params) -> PublicKey<E> {
+        let balance = value_balance_point(self.value_balance, params).negate();
+
+        PublicKey(self.cv_sum.add(&balance, params).into())
+    }
+
+    /// Produces the binding signature over `sighash`.
+    pub fn sign<R: Rng>(
+        &self,
+        sighash: &[u8],
+        rng: &mut R,
+        params: &E::Params
+    ) -> Signature
+    {
+        self.signing_key().sign(sighash, rng, BINDING_SIGNATURE_GENERATOR, params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, Unknown, edwards};
+    use primitives::ValueCommitment;
+    use redjubjub::PublicKey;
+    use super::*;
+
+    #[test]
+    fn test_binding_signature() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let spends: Vec<ValueCommitment<Bls12>> = [100, 2000, 30000].iter().map(|&v| {
+            ValueCommitment { value: v, randomness: rng.gen() }
+        }).collect();
+        let outputs: Vec<ValueCommitment<Bls12>> = [50000, 1].iter().map(|&v| {
+            ValueCommitment { value: v, randomness: rng.gen() }
+        }).collect();
+
+        let mut ctx = BindingContext::<Bls12>::new();
+        for cv in &spends {
+            ctx.add_spend(cv, params).unwrap();
+        }
+        for cv in &outputs {
+            ctx.add_output(cv, params).unwrap();
+        }
+
+        assert_eq!(ctx.value_balance(), 100 + 2000 + 30000 - 50000 - 1);
+
+        // The signing key matches the verification key.
+        let bvk = PublicKey::from_private(&ctx.signing_key(), BINDING_SIGNATURE_GENERATOR, params);
+        assert!(bvk.0 == ctx.verification_key(params).0);
+
+        let sighash = b"transaction sighash";
+        let sig = ctx.sign(sighash, rng, params);
+
+        let spend_cvs: Vec<edwards::Point<Bls12, Unknown>> = spends.iter().map(|cv| cv.cm(params).into()).collect();
+        let output_cvs: Vec<edwards::Point<Bls12, Unknown>> = outputs.iter().map(|cv| cv.cm(params).into()).collect();
+
+        assert!(verify(&spend_cvs, &output_cvs, ctx.value_balance(), sighash, &sig, params));
+
+        // The wrong value balance is rejected.
+        assert!(!verify(&spend_cvs, &output_cvs, ctx.value_balance() + 1, sighash, &sig, params));
+
+        // A missing output is rejected.
+        assert!(!verify(&spend_cvs, &output_cvs[1..], ctx.value_balance(), sighash, &sig, params));
+
+        // Swapping spends and outputs is rejected.
+        assert!(!verify(&output_cvs, &spend_cvs, -ctx.value_balance(), sighash, &sig, params));
+
+        // The signature is bound to the sighash.
+        assert!(!verify(&spend_cvs, &output_cvs, ctx.value_balance(), b"another sighash", &sig, params));
+    }
+
+    #[test]
+    fn test_value_balance_overflow() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut ctx = BindingContext::<Bls12>::new();
+        assert!(ctx.add_spend(&ValueCommitment { value: u64::max_value(), randomness: rng.gen() }, params).is_err());
+        assert!(ctx.add_spend(&ValueCommitment { value: i64::max_value() as u64, randomness: rng.gen() }, params).is_ok());
+        assert!(ctx.add_spend(&ValueCommitment { value: 1, randomness: rng.gen() }, params).is_err());
+        assert_eq!(ctx.value_balance(), i64::max_value());
+
+        // Extreme balances are still committed to correctly.
+        let mut ctx = BindingContext::<Bls12>::new();
+        ctx.add_output(&ValueCommitment { value: i64::max_value() as u64, randomness: rng.gen() }, params).unwrap();
+        ctx.add_output(&ValueCommitment { value: 1, randomness: rng.gen() }, params).unwrap();
+        assert_eq!(ctx.value_balance(), i64::min_value());
+
+        let bvk = PublicKey::from_private(&ctx.signing_key(), BINDING_SIGNATURE_GENERATOR, params);
+        assert!(bvk.0 == ctx.verification_key(params).0);
+    }
+}
@@ -0,0 +1,313 @@
+use pairing::{
+    Engine,
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use super::boolean::{
+    Boolean
+};
+
+use super::uint64::{
+    UInt64
+};
+
+use super::multieq::MultiEq;
+
+/*
+2.1.  Parameters
+   The following table summarizes various parameters and their ranges:
+                            | BLAKE2b          | BLAKE2s          |
+              --------------+------------------+------------------+
+               Bits in word | w = 64           | w = 32           |
+               Rounds in F  | r = 12           | r = 10           |
+               Block bytes  | bb = 128         | bb = 64          |
+               Hash bytes   | 1 <= nn <= 64    | 1 <= nn <= 32    |
+               Key bytes    | 0 <= kk <= 64    | 0 <= kk <= 32    |
+               Input bytes  | 0 <= ll < 2**128 | 0 <= ll < 2**64  |
+              --------------+------------------+------------------+
+               G Rotation   | (R1, R2, R3, R4) | (R1, R2, R3, R4) |
+                constants = | (32, 24, 16, 63) | (16, 12,  8,  7) |
+              --------------+------------------+------------------+
+*/
+
+const R1: usize = 32;
+const R2: usize = 24;
+const R3: usize = 16;
+const R4: usize = 63;
+
+/*
+   BLAKE2b uses the same message schedule SIGMA as BLAKE2s, with
+   rounds 10 and 11 reusing SIGMA[0] and SIGMA[1].
+*/
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+/*
+   BLAKE2b IV, from the fractional parts of the square roots of the
+   first eight primes (the same as SHA-512).
+*/
+
+const IV: [u64; 8] = [
+    0x6A09E667F3BCC908, 0xBB67AE8584CAA73B,
+    0x3C6EF372FE94F82B, 0xA54FF53A5F1D36F1,
+    0x510E527FADE682D1, 0x9B05688C2B3E6C1F,
+    0x1F83D9ABFB41BD6B, 0x5BE0CD19137E2179
+];
+
+/// The mixing function G, as in `blake2s` but over 64-bit words.
+fn mixing_g<E: Engine, CS: ConstraintSystem<E>, M>(
+    mut cs: M,
+    v: &mut [UInt64],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: &UInt64,
+    y: &UInt64
+) -> Result<(), SynthesisError>
+    where M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+{
+    v[a] = UInt64::addmany(cs.namespace(|| "mixing step 1"), &[v[a].clone(), v[b].clone(), x.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 2"), &v[a])?.rotr(R1);
+    v[c] = UInt64::addmany(cs.namespace(|| "mixing step 3"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 4"), &v[c])?.rotr(R2);
+    v[a] = UInt64::addmany(cs.namespace(|| "mixing step 5"), &[v[a].clone(), v[b].clone(), y.clone()])?;
+    v[d] = v[d].xor(cs.namespace(|| "mixing step 6"), &v[a])?.rotr(R3);
+    v[c] = UInt64::addmany(cs.namespace(|| "mixing step 7"), &[v[c].clone(), v[d].clone()])?;
+    v[b] = v[b].xor(cs.namespace(|| "mixing step 8"), &v[c])?.rotr(R4);
+
+    Ok(())
+}
+
+/// The compression function F, as in `blake2s` but with twelve
+/// rounds over 64-bit words.
+fn blake2b_compression<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    h: &mut [UInt64],
+    m: &[UInt64],
+    t: u64,
+    f: bool
+) -> Result<(), SynthesisError>
+{
+    assert_eq!(h.len(), 8);
+    assert_eq!(m.len(), 16);
+
+    let mut v = Vec::with_capacity(16);
+    v.extend_from_slice(h);
+    for iv in IV.iter() {
+        v.push(UInt64::constant(*iv));
+    }
+
+    assert_eq!(v.len(), 16);
+
+    // The offset counter is 128 bits, but inputs are never long
+    // enough for its high word (XORed into v[13]) to be nonzero.
+    v[12] = v[12].xor(cs.namespace(|| "first xor"), &UInt64::constant(t))?;
+
+    if f {
+        v[14] = v[14].xor(cs.namespace(|| "second xor"), &UInt64::constant(u64::max_value()))?;
+    }
+
+    {
+        let mut cs = MultiEq::new(&mut cs);
+
+        for i in 0..12 {
+            let mut cs = cs.namespace(|| format!("round {}", i));
+
+            let s = SIGMA[i % 10];
+
+            mixing_g(cs.namespace(|| "mixing invocation 1"), &mut v, 0, 4,  8, 12, &m[s[ 0]], &m[s[ 1]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 2"), &mut v, 1, 5,  9, 13, &m[s[ 2]], &m[s[ 3]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 3"), &mut v, 2, 6, 10, 14, &m[s[ 4]], &m[s[ 5]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 4"), &mut v, 3, 7, 11, 15, &m[s[ 6]], &m[s[ 7]])?;
+
+            mixing_g(cs.namespace(|| "mixing invocation 5"), &mut v, 0, 5, 10, 15, &m[s[ 8]], &m[s[ 9]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 6"), &mut v, 1, 6, 11, 12, &m[s[10]], &m[s[11]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 7"), &mut v, 2, 7,  8, 13, &m[s[12]], &m[s[13]])?;
+            mixing_g(cs.namespace(|| "mixing invocation 8"), &mut v, 3, 4,  9, 14, &m[s[14]], &m[s[15]])?;
+        }
+    }
+
+    for i in 0..8 {
+        let mut cs = cs.namespace(|| format!("h[{i}] ^ v[{i}] ^ v[{i} + 8]", i=i));
+
+        h[i] = h[i].xor(cs.namespace(|| "first xor"), &v[i])?;
+        h[i] = h[i].xor(cs.namespace(|| "second xor"), &v[i + 8])?;
+    }
+
+    Ok(())
+}
+
+/// Computes the unkeyed BLAKE2b hash of `input` with the given 16-byte
+/// personalization, returning the first `output_len` bytes of the
+/// digest. Input and output bits are in little-endian byte order, as
+/// for `blake2s`.
+pub fn blake2b<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+    output_len: usize,
+    personalization: &[u8]
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    use byteorder::{ByteOrder, LittleEndian};
+
+    assert!(output_len >= 1 && output_len <= 64);
+    assert_eq!(personalization.len(), 16);
+    assert!(input.len() % 8 == 0);
+
+    let mut h = Vec::with_capacity(8);
+    h.push(UInt64::constant(IV[0] ^ 0x01010000 ^ (output_len as u64)));
+    h.push(UInt64::constant(IV[1]));
+    h.push(UInt64::constant(IV[2]));
+    h.push(UInt64::constant(IV[3]));
+    h.push(UInt64::constant(IV[4]));
+    h.push(UInt64::constant(IV[5]));
+
+    // Personalization is stored here
+    h.push(UInt64::constant(IV[6] ^ LittleEndian::read_u64(&personalization[0..8])));
+    h.push(UInt64::constant(IV[7] ^ LittleEndian::read_u64(&personalization[8..16])));
+
+    let mut blocks: Vec<Vec<UInt64>> = vec![];
+
+    for block in input.chunks(1024) {
+        let mut this_block = Vec::with_capacity(16);
+        for word in block.chunks(64) {
+            let mut tmp = word.to_vec();
+            while tmp.len() < 64 {
+                tmp.push(Boolean::constant(false));
+            }
+            this_block.push(UInt64::from_bits(&tmp));
+        }
+        while this_block.len() < 16 {
+            this_block.push(UInt64::constant(0));
+        }
+        blocks.push(this_block);
+    }
+
+    if blocks.len() == 0 {
+        blocks.push((0..16).map(|_| UInt64::constant(0)).collect());
+    }
+
+    for (i, block) in blocks[0..blocks.len() - 1].iter().enumerate() {
+        let cs = cs.namespace(|| format!("block {}", i));
+
+        blake2b_compression(cs, &mut h, block, ((i as u64) + 1) * 128, false)?;
+    }
+
+    {
+        let cs = cs.namespace(|| "final block");
+
+        blake2b_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+    }
+
+    Ok(h.iter().flat_map(|b| b.into_bits()).take(output_len * 8).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use pairing::bls12_381::{Bls12};
+    use ::circuit::boolean::{Boolean, AllocatedBit};
+    use ::circuit::test::TestConstraintSystem;
+    use super::blake2b;
+    use bellman::{ConstraintSystem};
+    use blake2_rfc::blake2b::Blake2b;
+
+    #[test]
+    fn test_blank_hash() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = vec![];
+        let out = blake2b(&mut cs, &input_bits, 64, b"1234567890123456").unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+
+        let expected = Blake2b::with_params(64, &[], &[], b"1234567890123456").finalize();
+
+        let mut out = out.into_iter();
+        for b in expected.as_ref().iter() {
+            for i in (0..8).rev() {
+                let c = out.next().unwrap().get_value().unwrap();
+
+                assert_eq!(c, (b >> i) & 1u8 == 1u8);
+            }
+        }
+        assert!(out.next().is_none());
+    }
+
+    #[test]
+    fn test_blake2b_constant_constraints() {
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let input_bits: Vec<_> = (0..1024).map(|_| Boolean::constant(rng.gen())).collect();
+        blake2b(&mut cs, &input_bits, 64, b"1234567890123456").unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_blake2b() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for input_len in (0..16).chain((16..300).filter(|a| a % 32 == 0))
+        {
+            let output_len = rng.gen_range(1, 65);
+
+            let mut h = Blake2b::with_params(output_len, &[], &[], b"1234567890123456");
+
+            let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+
+            h.update(&data);
+
+            let hash_result = h.finalize();
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let mut input_bits = vec![];
+
+            for (byte_i, input_byte) in data.into_iter().enumerate() {
+                for bit_i in (0..8).rev() {
+                    let cs = cs.namespace(|| format!("input bit {} {}", byte_i, bit_i));
+
+                    input_bits.push(AllocatedBit::alloc(cs, Some((input_byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+                }
+            }
+
+            let r = blake2b(&mut cs, &input_bits, output_len, b"1234567890123456").unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(r.len(), output_len * 8);
+
+            let mut s = hash_result.as_ref().iter()
+                                            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8));
+
+            for b in r {
+                match b {
+                    Boolean::Is(b) => {
+                        assert!(s.next().unwrap() == b.get_value().unwrap());
+                    },
+                    Boolean::Not(b) => {
+                        assert!(s.next().unwrap() != b.get_value().unwrap());
+                    },
+                    Boolean::Constant(b) => {
+                        assert!(input_len == 0);
+                        assert!(s.next().unwrap() == b);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -260,31 +260,98 @@ fn blake2s_compression<E: Engine, CS: ConstraintSystem<E>>(
 */
 
 pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
-    mut cs: CS,
+    cs: CS,
     input: &[Boolean],
     personalization: &[u8]
 ) -> Result<Vec<Boolean>, SynthesisError>
 {
-    use byteorder::{ByteOrder, LittleEndian};
-
     assert_eq!(personalization.len(), 8);
+
+    blake2s_with_params(
+        cs,
+        input,
+        &[],
+        &constant_bytes(&[0; 8]),
+        &constant_bytes(personalization),
+        32
+    )
+}
+
+/// Returns constant bits for `bytes`, in the little-endian byte order
+/// representation used for the inputs and outputs of `blake2s`.
+pub fn constant_bytes(bytes: &[u8]) -> Vec<Boolean> {
+    bytes.iter()
+         .flat_map(|&byte| (0..8).rev().map(move |i| Boolean::constant((byte >> i) & 1u8 == 1u8)))
+         .collect()
+}
+
+/// BLAKE2s with all of the parameters of the unkeyed and keyed
+/// hash. The key is up to 32 bytes long and may be empty, the salt
+/// and personalization are 8 bytes long, and the digest is the first
+/// `output_len` bytes of the hash state, for `output_len` from 1 to
+/// 32. The key, salt and personalization may be constant or
+/// allocated; constant words cost no constraints.
+///
+/// BLAKE2s is defined over bytes, so the input must be a whole
+/// number of bytes long. All bits are in the same byte order as
+/// for `blake2s`.
+pub fn blake2s_with_params<E: Engine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    input: &[Boolean],
+    key: &[Boolean],
+    salt: &[Boolean],
+    personalization: &[Boolean],
+    output_len: usize
+) -> Result<Vec<Boolean>, SynthesisError>
+{
+    assert!(output_len >= 1 && output_len <= 32);
+    assert!(key.len() % 8 == 0 && key.len() <= 256);
+    assert_eq!(salt.len(), 64);
+    assert_eq!(personalization.len(), 64);
     assert!(input.len() % 8 == 0);
 
+    let key_len = key.len() / 8;
+
     let mut h = Vec::with_capacity(8);
-    h.push(UInt32::constant(0x6A09E667 ^ 0x01010000 ^ 32));
+    h.push(UInt32::constant(0x6A09E667 ^ 0x01010000 ^ ((key_len as u32) << 8) ^ (output_len as u32)));
     h.push(UInt32::constant(0xBB67AE85));
     h.push(UInt32::constant(0x3C6EF372));
     h.push(UInt32::constant(0xA54FF53A));
-    h.push(UInt32::constant(0x510E527F));
-    h.push(UInt32::constant(0x9B05688C));
+
+    // Salt is stored here
+    h.push(UInt32::constant(0x510E527F).xor(
+        cs.namespace(|| "first salt word"),
+        &UInt32::from_bits(&salt[0..32])
+    )?);
+    h.push(UInt32::constant(0x9B05688C).xor(
+        cs.namespace(|| "second salt word"),
+        &UInt32::from_bits(&salt[32..64])
+    )?);
 
     // Personalization is stored here
-    h.push(UInt32::constant(0x1F83D9AB ^ LittleEndian::read_u32(&personalization[0..4])));
-    h.push(UInt32::constant(0x5BE0CD19 ^ LittleEndian::read_u32(&personalization[4..8])));
+    h.push(UInt32::constant(0x1F83D9AB).xor(
+        cs.namespace(|| "first personalization word"),
+        &UInt32::from_bits(&personalization[0..32])
+    )?);
+    h.push(UInt32::constant(0x5BE0CD19).xor(
+        cs.namespace(|| "second personalization word"),
+        &UInt32::from_bits(&personalization[32..64])
+    )?);
+
+    // A nonempty key is padded with zeroes to a full block,
+    // which is processed before the input
+    let mut data = Vec::with_capacity(512 + input.len());
+    if key_len > 0 {
+        data.extend_from_slice(key);
+        while data.len() < 512 {
+            data.push(Boolean::constant(false));
+        }
+    }
+    data.extend_from_slice(input);
 
     let mut blocks: Vec<Vec<UInt32>> = vec![];
 
-    for block in input.chunks(512) {
+    for block in data.chunks(512) {
         let mut this_block = Vec::with_capacity(16);
         for word in block.chunks(32) {
             let mut tmp = word.to_vec();
@@ -312,10 +379,11 @@ pub fn blake2s<E: Engine, CS: ConstraintSystem<E>>(
     {
         let cs = cs.namespace(|| "final block");
 
-        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (input.len() / 8) as u64, true)?;
+        // The offset includes the key block, if there is one
+        blake2s_compression(cs, &mut h, &blocks[blocks.len() - 1], (data.len() / 8) as u64, true)?;
     }
 
-    Ok(h.iter().flat_map(|b| b.into_bits()).collect())
+    Ok(h.iter().flat_map(|b| b.into_bits()).take(output_len * 8).collect())
 }
 
 #[cfg(test)]
@@ -324,7 +392,7 @@ mod test {
     use pairing::bls12_381::{Bls12};
     use ::circuit::boolean::{Boolean, AllocatedBit};
     use ::circuit::test::TestConstraintSystem;
-    use super::blake2s;
+    use super::{blake2s, blake2s_with_params, constant_bytes};
     use bellman::{ConstraintSystem};
     use blake2_rfc::blake2s::Blake2s;
 
@@ -435,4 +503,106 @@ mod test {
             }
         }
     }
+
+    fn alloc_bytes<CS: ConstraintSystem<Bls12>>(mut cs: CS, bytes: &[u8]) -> Vec<Boolean> {
+        let mut bits = vec![];
+
+        for (byte_i, byte) in bytes.iter().enumerate() {
+            for bit_i in (0..8).rev() {
+                let cs = cs.namespace(|| format!("bit {} {}", byte_i, bit_i));
+
+                bits.push(AllocatedBit::alloc(cs, Some((byte >> bit_i) & 1u8 == 1u8)).unwrap().into());
+            }
+        }
+
+        bits
+    }
+
+    fn assert_bits_eq(bits: &[Boolean], bytes: &[u8]) {
+        assert_eq!(bits.len(), bytes.len() * 8);
+
+        let expected = bytes.iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1u8 == 1u8));
+
+        for (b, e) in bits.iter().zip(expected) {
+            assert_eq!(b.get_value().unwrap(), e);
+        }
+    }
+
+    #[test]
+    fn test_blake2s_with_params() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &input_len in &[0, 1, 31, 32, 63, 64, 65, 128, 200] {
+            for &key_len in &[0, 1, 16, 32] {
+                let output_len = rng.gen_range(1, 33);
+
+                let data: Vec<u8> = (0..input_len).map(|_| rng.gen()).collect();
+                let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+                let salt: [u8; 8] = rng.gen();
+                let personalization: [u8; 8] = rng.gen();
+
+                let mut h = Blake2s::with_params(output_len, &key, &salt, &personalization);
+                h.update(&data);
+                let hash_result = h.finalize();
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+
+                let input_bits = alloc_bytes(cs.namespace(|| "input"), &data);
+                let key_bits = alloc_bytes(cs.namespace(|| "key"), &key);
+
+                // Constant salt and personalization
+                let r = blake2s_with_params(
+                    cs.namespace(|| "constant parameters"),
+                    &input_bits,
+                    &key_bits,
+                    &constant_bytes(&salt),
+                    &constant_bytes(&personalization),
+                    output_len
+                ).unwrap();
+                assert_bits_eq(&r, hash_result.as_ref());
+
+                // Allocated salt and personalization
+                let salt_bits = alloc_bytes(cs.namespace(|| "salt"), &salt);
+                let personalization_bits = alloc_bytes(cs.namespace(|| "personalization"), &personalization);
+                let r = blake2s_with_params(
+                    cs.namespace(|| "allocated parameters"),
+                    &input_bits,
+                    &key_bits,
+                    &salt_bits,
+                    &personalization_bits,
+                    output_len
+                ).unwrap();
+                assert_bits_eq(&r, hash_result.as_ref());
+
+                assert!(cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_blake2s_with_params_matches_blake2s() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let data: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let input_bits = alloc_bytes(cs.namespace(|| "input"), &data);
+
+        let a = blake2s(cs.namespace(|| "blake2s"), &input_bits, b"12345678").unwrap();
+        let num_constraints = cs.num_constraints();
+
+        let b = blake2s_with_params(
+            cs.namespace(|| "blake2s_with_params"),
+            &input_bits,
+            &[],
+            &constant_bytes(&[0; 8]),
+            &constant_bytes(b"12345678"),
+            32
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - num_constraints, num_constraints - 800);
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.get_value(), b.get_value());
+        }
+    }
 }
@@ -0,0 +1,232 @@
+use pairing::{
+    Engine
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem
+};
+
+use jubjub::{
+    JubjubEngine
+};
+
+use super::Assignment;
+use super::boolean::{
+    AllocatedBit,
+    Boolean
+};
+use super::num::AllocatedNum;
+use super::pedersen_hash;
+
+/// A two-to-one hash used to compute the nodes of a Merkle tree
+/// in the circuit.
+pub trait MerkleHash<E: Engine> {
+    /// Computes the parent of `left` and `right` at the given depth
+    /// of the tree, where depth 0 combines two leaves.
+    fn hash<CS>(
+        &self,
+        cs: CS,
+        depth: usize,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>;
+}
+
+/// The hash of the Sapling note commitment tree: the x-coordinate of
+/// the Pedersen hash of both children, personalized by depth. This
+/// matches `merkle_tree::merkle_hash` outside of the circuit.
+pub struct PedersenMerkleHash<'a, E: JubjubEngine + 'a> {
+    pub params: &'a E::Params
+}
+
+impl<'a, E: JubjubEngine + 'a> MerkleHash<E> for PedersenMerkleHash<'a, E> {
+    fn hash<CS>(
+        &self,
+        mut cs: CS,
+        depth: usize,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        // We don't need to be strict, because the function is
+        // collision-resistant. If the prover witnesses a congruency,
+        // they will be unable to find an authentication path in the
+        // tree with high probability.
+        let mut preimage = vec![];
+        preimage.extend(left.into_bits_le(cs.namespace(|| "xl into bits"))?);
+        preimage.extend(right.into_bits_le(cs.namespace(|| "xr into bits"))?);
+
+        Ok(pedersen_hash::pedersen_hash(
+            cs.namespace(|| "computation of pedersen hash"),
+            pedersen_hash::Personalization::MerkleTree(depth),
+            &preimage,
+            self.params
+        )?.get_x().clone()) // Injective encoding
+    }
+}
+
+/// Computes the root of a Merkle tree from a leaf and its
+/// authentication path, hashing with `hasher`. Each element of the
+/// path is the sibling at that depth and whether the current subtree
+/// is the right child, as in `merkle_tree::CommitmentTreePath`; the
+/// depth of the tree is the length of the path.
+///
+/// Returns the root and the position of the leaf, as bits with the
+/// least significant first. The caller decides whether to expose
+/// the root as an input.
+pub fn compute_root<E, CS, H>(
+    mut cs: CS,
+    hasher: &H,
+    leaf: &AllocatedNum<E>,
+    auth_path: &[Option<(E::Fr, bool)>]
+) -> Result<(AllocatedNum<E>, Vec<Boolean>), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>,
+          H: MerkleHash<E>
+{
+    // This will store (least significant bit first)
+    // the position of the leaf in the tree.
+    let mut position_bits = vec![];
+
+    let mut cur = leaf.clone();
+
+    for (i, e) in auth_path.iter().enumerate() {
+        let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
+
+        // Determines if the current subtree is the "right" leaf at this
+        // depth of the tree.
+        let cur_is_right = Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| "position bit"),
+            e.map(|e| e.1)
+        )?);
+
+        // Push this boolean for the caller
+        position_bits.push(cur_is_right.clone());
+
+        // Witness the authentication path element adjacent
+        // at this depth.
+        let path_element = AllocatedNum::alloc(
+            cs.namespace(|| "path element"),
+            || {
+                Ok(e.get()?.0)
+            }
+        )?;
+
+        // Swap the two if the current subtree is on the right
+        let (xl, xr) = AllocatedNum::conditionally_reverse(
+            cs.namespace(|| "conditional reversal of preimage"),
+            &cur,
+            &path_element,
+            &cur_is_right
+        )?;
+
+        // Compute the new subtree value
+        cur = hasher.hash(cs, i, &xl, &xr)?;
+    }
+
+    assert_eq!(position_bits.len(), auth_path.len());
+
+    Ok((cur, position_bits))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::Field;
+    use pairing::bls12_381::{Bls12, Fr};
+    use bellman::ConstraintSystem;
+    use circuit::test::*;
+    use jubjub::JubjubBls12;
+    use merkle_tree::CommitmentTreePath;
+    use super::*;
+
+    /// A toy hash, the product of both children, to check that the
+    /// gadget is generic over the hash.
+    struct ProductHash;
+
+    impl<E: Engine> MerkleHash<E> for ProductHash {
+        fn hash<CS>(
+            &self,
+            mut cs: CS,
+            _depth: usize,
+            left: &AllocatedNum<E>,
+            right: &AllocatedNum<E>
+        ) -> Result<AllocatedNum<E>, SynthesisError>
+            where CS: ConstraintSystem<E>
+        {
+            left.mul(cs.namespace(|| "product"), right)
+        }
+    }
+
+    #[test]
+    fn test_pedersen_merkle_path() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &depth in &[0, 1, 4, 32] {
+            let auth_path: Vec<Option<(Fr, bool)>> = (0..depth).map(|_| Some((rng.gen(), rng.gen()))).collect();
+            let leaf: Fr = rng.gen();
+
+            let path = CommitmentTreePath::<Bls12> {
+                auth_path: auth_path.clone(),
+                position: 0
+            };
+            let expected_root = path.root(leaf, params);
+
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+            let leaf_num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+
+            let (root, position_bits) = compute_root(
+                cs.namespace(|| "merkle"),
+                &PedersenMerkleHash { params: params },
+                &leaf_num,
+                &auth_path
+            ).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(root.get_value().unwrap(), expected_root);
+            assert_eq!(position_bits.len(), depth);
+            for (b, e) in position_bits.iter().zip(auth_path.iter()) {
+                assert_eq!(b.get_value().unwrap(), e.unwrap().1);
+            }
+
+            if depth > 0 {
+                cs.set("merkle/merkle tree hash 0/path element/num", Fr::one());
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_generic_merkle_path() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let auth_path: Vec<Option<(Fr, bool)>> = (0..10).map(|_| Some((rng.gen(), rng.gen()))).collect();
+        let leaf: Fr = rng.gen();
+
+        let mut expected_root = leaf;
+        for e in &auth_path {
+            expected_root.mul_assign(&e.unwrap().0);
+        }
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let leaf_num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+
+        let (root, _) = compute_root(
+            cs.namespace(|| "merkle"),
+            &ProductHash,
+            &leaf_num,
+            &auth_path
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(root.get_value().unwrap(), expected_root);
+
+        // Per level: the position bit, two for the reversal and one
+        // for the product
+        assert_eq!(cs.num_constraints(), 10 * 4);
+    }
+}
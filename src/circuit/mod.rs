@@ -4,11 +4,16 @@ pub mod test;
 pub mod boolean;
 pub mod multieq;
 pub mod uint32;
+pub mod uint64;
 pub mod blake2s;
+pub mod blake2b;
+pub mod sha256;
 pub mod num;
 pub mod lookup;
 pub mod ecc;
 pub mod pedersen_hash;
+pub mod merkle;
+pub mod poseidon;
 pub mod multipack;
 
 pub mod sapling;
@@ -0,0 +1,383 @@
+use pairing::{
+    Engine,
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination,
+    Variable
+};
+
+use poseidon::PoseidonParams;
+
+use super::Assignment;
+use super::num::AllocatedNum;
+use super::merkle::MerkleHash;
+
+/// An element of the state, as an affine combination of the
+/// variables in the basis, whose first element is `CS::one()`.
+/// The terms are merged by basis index, so that the linear layers
+/// don't duplicate terms and cost no constraints at all. After a
+/// full round every element has at most `width + 1` terms.
+struct Combination<E: Engine> {
+    value: Option<E::Fr>,
+    /// Indices into the basis with their coefficients, sorted by
+    /// index and without zero coefficients.
+    terms: Vec<(usize, E::Fr)>
+}
+
+impl<E: Engine> Combination<E> {
+    fn constant(value: E::Fr) -> Self {
+        Combination {
+            value: Some(value),
+            terms: if value.is_zero() { vec![] } else { vec![(0, value)] }
+        }
+    }
+
+    fn variable(index: usize, value: Option<E::Fr>) -> Self {
+        Combination {
+            value: value,
+            terms: vec![(index, E::Fr::one())]
+        }
+    }
+
+    fn add_constant(&mut self, c: &E::Fr) {
+        self.add_scaled(&Combination::constant(*c), &E::Fr::one());
+    }
+
+    fn add_scaled(&mut self, other: &Self, coeff: &E::Fr) {
+        self.value = match (self.value, other.value) {
+            (Some(mut a), Some(mut b)) => {
+                b.mul_assign(coeff);
+                a.add_assign(&b);
+
+                Some(a)
+            },
+            _ => None
+        };
+
+        let terms = {
+            let a = &self.terms;
+            let b = &other.terms;
+            let mut terms = Vec::with_capacity(a.len() + b.len());
+            let mut i = 0;
+            let mut j = 0;
+
+            while i < a.len() || j < b.len() {
+                if j == b.len() || (i < a.len() && a[i].0 < b[j].0) {
+                    terms.push(a[i]);
+                    i += 1;
+                } else {
+                    let (index, mut c) = b[j];
+                    c.mul_assign(coeff);
+                    j += 1;
+
+                    if i < a.len() && a[i].0 == index {
+                        c.add_assign(&a[i].1);
+                        i += 1;
+                    }
+
+                    if !c.is_zero() {
+                        terms.push((index, c));
+                    }
+                }
+            }
+
+            terms
+        };
+
+        self.terms = terms;
+    }
+
+    fn lc(&self, basis: &[Variable]) -> LinearCombination<E> {
+        let mut lc = LinearCombination::zero();
+
+        for &(index, coeff) in &self.terms {
+            lc = lc + (coeff, basis[index]);
+        }
+
+        lc
+    }
+}
+
+/// Computes x^5 in three constraints, and adds the result
+/// to the basis.
+fn sbox<E, CS>(
+    mut cs: CS,
+    basis: &mut Vec<Variable>,
+    x: &Combination<E>
+) -> Result<Combination<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let x_lc = x.lc(basis);
+
+    let x2 = AllocatedNum::alloc(cs.namespace(|| "x^2"), || {
+        let mut tmp = *x.value.get()?;
+        tmp.square();
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "x^2 computation",
+        |_| x_lc.clone(),
+        |_| x_lc.clone(),
+        |lc| lc + x2.get_variable()
+    );
+
+    let x4 = x2.square(cs.namespace(|| "x^4"))?;
+
+    let x5 = AllocatedNum::alloc(cs.namespace(|| "x^5"), || {
+        let mut tmp = *x4.get_value().get()?;
+        tmp.mul_assign(x.value.get()?);
+
+        Ok(tmp)
+    })?;
+
+    cs.enforce(
+        || "x^5 computation",
+        |lc| lc + x4.get_variable(),
+        |_| x_lc,
+        |lc| lc + x5.get_variable()
+    );
+
+    basis.push(x5.get_variable());
+
+    Ok(Combination::variable(basis.len() - 1, x5.get_value()))
+}
+
+fn permutation<E, CS>(
+    mut cs: CS,
+    basis: &mut Vec<Variable>,
+    state: &mut Vec<Combination<E>>,
+    params: &PoseidonParams<E>
+) -> Result<(), SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    for round in 0..params.num_rounds() {
+        let cs = &mut cs.namespace(|| format!("round {}", round));
+
+        for (s, c) in state.iter_mut().zip(params.round_constants(round)) {
+            s.add_constant(c);
+        }
+
+        if params.is_full_round(round) {
+            for (i, s) in state.iter_mut().enumerate() {
+                let out = sbox(cs.namespace(|| format!("sbox {}", i)), basis, s)?;
+                *s = out;
+            }
+        } else {
+            let out = sbox(cs.namespace(|| "sbox 0"), basis, &state[0])?;
+            state[0] = out;
+        }
+
+        let mixed: Vec<Combination<E>> = params.mds().iter().map(|row| {
+            let mut acc = Combination::constant(E::Fr::zero());
+            for (m, s) in row.iter().zip(state.iter()) {
+                acc.add_scaled(s, m);
+            }
+
+            acc
+        }).collect();
+
+        *state = mixed;
+    }
+
+    Ok(())
+}
+
+/// Hashes a sequence of field elements into one, matching
+/// `poseidon::poseidon_hash` outside of the circuit. The S-boxes
+/// cost three constraints each and the output one more, so a
+/// permutation of width `t` costs `3 * (t * full_rounds +
+/// partial_rounds)` constraints.
+pub fn poseidon_hash<E, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<E>],
+    params: &PoseidonParams<E>
+) -> Result<AllocatedNum<E>, SynthesisError>
+    where E: Engine,
+          CS: ConstraintSystem<E>
+{
+    let mut basis = vec![CS::one()];
+    let mut state: Vec<Combination<E>> = (0..params.width()).map(|_| {
+        Combination::constant(E::Fr::zero())
+    }).collect();
+
+    // Domain separation between inputs of different lengths
+    state[0] = Combination::constant(E::Fr::from_repr(
+        <E::Fr as PrimeField>::Repr::from(inputs.len() as u64)
+    ).expect("the length is in the field"));
+
+    if inputs.is_empty() {
+        permutation(cs.namespace(|| "permutation 0"), &mut basis, &mut state, params)?;
+    }
+
+    for (i, chunk) in inputs.chunks(params.rate()).enumerate() {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            basis.push(x.get_variable());
+            s.add_scaled(&Combination::variable(basis.len() - 1, x.get_value()), &E::Fr::one());
+        }
+
+        permutation(cs.namespace(|| format!("permutation {}", i)), &mut basis, &mut state, params)?;
+    }
+
+    let result = AllocatedNum::alloc(cs.namespace(|| "result"), || {
+        Ok(*state[1].value.get()?)
+    })?;
+
+    cs.enforce(
+        || "result computation",
+        |_| state[1].lc(&basis),
+        |lc| lc + CS::one(),
+        |lc| lc + result.get_variable()
+    );
+
+    Ok(result)
+}
+
+/// A Merkle tree hash computing the Poseidon hash of both children.
+/// It is not personalized by depth. This matches
+/// `poseidon::poseidon_hash(&[left, right], params)` outside of
+/// the circuit.
+pub struct PoseidonMerkleHash<'a, E: Engine + 'a> {
+    pub params: &'a PoseidonParams<E>
+}
+
+impl<'a, E: Engine + 'a> MerkleHash<E> for PoseidonMerkleHash<'a, E> {
+    fn hash<CS>(
+        &self,
+        cs: CS,
+        _depth: usize,
+        left: &AllocatedNum<E>,
+        right: &AllocatedNum<E>
+    ) -> Result<AllocatedNum<E>, SynthesisError>
+        where CS: ConstraintSystem<E>
+    {
+        poseidon_hash(cs, &[left.clone(), right.clone()], self.params)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::{Bls12, Fr};
+    use circuit::test::*;
+    use circuit::merkle::compute_root;
+    use poseidon;
+    use super::*;
+
+    #[test]
+    fn test_poseidon_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &width in &[2, 3, 5] {
+            let params = PoseidonParams::<Bls12>::new(width, 8, 57);
+
+            for len in 0..8 {
+                let inputs: Vec<Fr> = (0..len).map(|_| rng.gen()).collect();
+
+                let mut cs = TestConstraintSystem::<Bls12>::new();
+                let input_nums: Vec<AllocatedNum<Bls12>> = inputs.iter().enumerate().map(|(i, x)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*x)).unwrap()
+                }).collect();
+
+                let result = poseidon_hash(cs.namespace(|| "hash"), &input_nums, &params).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(result.get_value().unwrap(), poseidon::poseidon_hash(&inputs, &params));
+
+                let num_permutations = if len == 0 { 1 } else { (len + width - 2) / (width - 1) };
+                assert_eq!(cs.num_constraints(), num_permutations * 3 * (width * 8 + 57) + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_combination_merges_terms() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let x: Fr = rng.gen();
+        let y: Fr = rng.gen();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+
+        // 3 + x_2 + 2 x_5
+        let mut a = Combination::<Bls12>::constant(Fr::from_str("3").unwrap());
+        a.add_scaled(&Combination::variable(2, Some(x)), &Fr::one());
+        a.add_scaled(&Combination::variable(5, Some(y)), &Fr::from_str("2").unwrap());
+        assert_eq!(a.terms.len(), 3);
+
+        // Adding x_2 - 3 again merges into the existing terms, and
+        // cancels the constant.
+        let mut b = Combination::<Bls12>::variable(2, Some(x));
+        b.add_constant(&Fr::from_str("3").unwrap());
+        b.add_scaled(&Combination::constant(Fr::from_str("6").unwrap()), &minus_one);
+        a.add_scaled(&b, &Fr::one());
+
+        assert_eq!(a.terms.iter().map(|&(i, _)| i).collect::<Vec<_>>(), vec![2, 5]);
+        assert_eq!(a.terms[0].1, Fr::from_str("2").unwrap());
+        assert_eq!(a.terms[1].1, Fr::from_str("2").unwrap());
+
+        let mut expected = x;
+        expected.add_assign(&y);
+        expected.double();
+        assert_eq!(a.value.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_poseidon_hash_unsatisfied() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bls12>::recommended_width_3();
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(rng.gen())).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(rng.gen())).unwrap();
+
+        poseidon_hash(cs.namespace(|| "hash"), &[a, b], &params).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 244);
+
+        // Tampering with an S-box in a partial round
+        cs.set("hash/permutation 0/round 30/sbox 0/x^5/num", rng.gen());
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_poseidon_merkle_path() {
+        let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bls12>::recommended_width_3();
+
+        let auth_path: Vec<Option<(Fr, bool)>> = (0..8).map(|_| Some((rng.gen(), rng.gen()))).collect();
+        let leaf: Fr = rng.gen();
+
+        let mut expected_root = leaf;
+        for e in &auth_path {
+            let (sibling, cur_is_right) = e.unwrap();
+            expected_root = if cur_is_right {
+                poseidon::poseidon_hash(&[sibling, expected_root], &params)
+            } else {
+                poseidon::poseidon_hash(&[expected_root, sibling], &params)
+            };
+        }
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        let leaf_num = AllocatedNum::alloc(cs.namespace(|| "leaf"), || Ok(leaf)).unwrap();
+
+        let (root, _) = compute_root(
+            cs.namespace(|| "merkle"),
+            &PoseidonMerkleHash { params: &params },
+            &leaf_num,
+            &auth_path
+        ).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(root.get_value().unwrap(), expected_root);
+    }
+}
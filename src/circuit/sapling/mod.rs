@@ -22,12 +22,11 @@ use primitives::{
     PaymentAddress
 };
 
-use super::Assignment;
 use super::boolean;
 use super::ecc;
 use super::pedersen_hash;
 use super::blake2s;
-use super::num;
+use super::merkle;
 
 /// This is an instance of the `Spend` circuit.
 pub struct Spend<'a, E: JubjubEngine> {
@@ -220,7 +219,7 @@ impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
 
             ecc::EdwardsPoint::witness(
                 cs.namespace(|| "witness g_d"),
-                self.payment_address.as_ref().and_then(|a| a.g_d(params)),
+                self.payment_address.as_ref().and_then(|a| a.g_d(params).ok()),
                 self.params
             )?
         };
@@ -288,65 +287,16 @@ impl<'a, E: JubjubEngine> Circuit<E> for Spend<'a, E> {
             )?;
         }
 
-        let tree_depth = self.auth_path.len();
-
-        // This will store (least significant bit first)
-        // the position of the note in the tree, for use
-        // in nullifier computation.
-        let mut position_bits = vec![];
-
-        // This is an injective encoding, as cur is a
-        // point in the prime order subgroup.
-        let mut cur = cm.get_x().clone();
-
-        for (i, e) in self.auth_path.into_iter().enumerate() {
-            let cs = &mut cs.namespace(|| format!("merkle tree hash {}", i));
-
-            // Determines if the current subtree is the "right" leaf at this
-            // depth of the tree.
-            let cur_is_right = boolean::Boolean::from(boolean::AllocatedBit::alloc(
-                cs.namespace(|| "position bit"),
-                e.map(|e| e.1)
-            )?);
-
-            // Push this boolean for nullifier computation later
-            position_bits.push(cur_is_right.clone());
-
-            // Witness the authentication path element adjacent
-            // at this depth.
-            let path_element = num::AllocatedNum::alloc(
-                cs.namespace(|| "path element"),
-                || {
-                    Ok(e.get()?.0)
-                }
-            )?;
-
-            // Swap the two if the current subtree is on the right
-            let (xl, xr) = num::AllocatedNum::conditionally_reverse(
-                cs.namespace(|| "conditional reversal of preimage"),
-                &cur,
-                &path_element,
-                &cur_is_right
-            )?;
-
-            // We don't need to be strict, because the function is
-            // collision-resistant. If the prover witnesses a congruency,
-            // they will be unable to find an authentication path in the
-            // tree with high probability.
-            let mut preimage = vec![];
-            preimage.extend(xl.into_bits_le(cs.namespace(|| "xl into bits"))?);
-            preimage.extend(xr.into_bits_le(cs.namespace(|| "xr into bits"))?);
-
-            // Compute the new subtree value
-            cur = pedersen_hash::pedersen_hash(
-                cs.namespace(|| "computation of pedersen hash"),
-                pedersen_hash::Personalization::MerkleTree(i),
-                &preimage,
-                self.params
-            )?.get_x().clone(); // Injective encoding
-        }
-
-        assert_eq!(position_bits.len(), tree_depth);
+        // Compute the anchor, along with the position of the note
+        // in the tree (least significant bit first) for use in
+        // nullifier computation. The leaf is an injective encoding,
+        // as cm is a point in the prime order subgroup.
+        let (cur, position_bits) = merkle::compute_root(
+            &mut cs,
+            &merkle::PedersenMerkleHash { params: self.params },
+            cm.get_x(),
+            &self.auth_path
+        )?;
 
         // Expose the anchor
         cur.inputize(cs.namespace(|| "anchor"))?;
@@ -428,7 +378,7 @@ impl<'a, E: JubjubEngine> Circuit<E> for Output<'a, E> {
             // curve.
             let g_d = ecc::EdwardsPoint::witness(
                 cs.namespace(|| "witness g_d"),
-                self.payment_address.as_ref().and_then(|a| a.g_d(params)),
+                self.payment_address.as_ref().and_then(|a| a.g_d(params).ok()),
                 self.params
             )?;
 
@@ -575,7 +525,7 @@ fn test_input_circuit_with_bls12_381() {
     loop {
         let diversifier = ::primitives::Diversifier(rng.gen());
 
-        if let Some(p) = viewing_key.into_payment_address(
+        if let Ok(p) = viewing_key.into_payment_address(
             diversifier,
             params
         )
@@ -594,8 +544,8 @@ fn test_input_circuit_with_bls12_381() {
 
         let instance = Spend {
             params: params,
-            value_commitment: Some(value_commitment.clone()),
-            proof_generation_key: Some(proof_generation_key.clone()),
+            value_commitment: Some(value_commitment.clone_secret()),
+            proof_generation_key: Some(proof_generation_key.clone_secret()),
             payment_address: Some(payment_address.clone()),
             commitment_randomness: Some(commitment_randomness),
             auth_path: auth_path.clone()
@@ -696,7 +646,7 @@ fn test_output_circuit_with_bls12_381() {
     loop {
         let diversifier = ::primitives::Diversifier(rng.gen());
 
-        if let Some(p) = viewing_key.into_payment_address(
+        if let Ok(p) = viewing_key.into_payment_address(
             diversifier,
             params
         )
@@ -714,7 +664,7 @@ fn test_output_circuit_with_bls12_381() {
 
         let instance = Output {
             params: params,
-            value_commitment: Some(value_commitment.clone()),
+            value_commitment: Some(value_commitment.clone_secret()),
             payment_address: Some(payment_address.clone()),
             commitment_randomness: Some(commitment_randomness),
             esk: Some(esk.clone())
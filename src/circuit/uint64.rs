@@ -0,0 +1,524 @@
+use pairing::{
+    Engine,
+    Field,
+    PrimeField
+};
+
+use bellman::{
+    SynthesisError,
+    ConstraintSystem,
+    LinearCombination
+};
+
+use super::boolean::{
+    Boolean,
+    AllocatedBit
+};
+
+use super::multieq::MultiEq;
+
+/// Represents an interpretation of 64 `Boolean` objects as an
+/// unsigned integer.
+#[derive(Clone)]
+pub struct UInt64 {
+    // Least significant bit first
+    bits: Vec<Boolean>,
+    value: Option<u64>
+}
+
+impl UInt64 {
+    /// Construct a constant `UInt64` from a `u64`
+    pub fn constant(value: u64) -> Self
+    {
+        let mut bits = Vec::with_capacity(64);
+
+        let mut tmp = value;
+        for _ in 0..64 {
+            if tmp & 1 == 1 {
+                bits.push(Boolean::constant(true))
+            } else {
+                bits.push(Boolean::constant(false))
+            }
+
+            tmp >>= 1;
+        }
+
+        UInt64 {
+            bits: bits,
+            value: Some(value)
+        }
+    }
+
+    /// Allocate a `UInt64` in the constraint system
+    pub fn alloc<E, CS>(
+        mut cs: CS,
+        value: Option<u64>
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let values = match value {
+            Some(mut val) => {
+                let mut v = Vec::with_capacity(64);
+
+                for _ in 0..64 {
+                    v.push(Some(val & 1 == 1));
+                    val >>= 1;
+                }
+
+                v
+            },
+            None => vec![None; 64]
+        };
+
+        let bits = values.into_iter()
+                         .enumerate()
+                         .map(|(i, v)| {
+                            Ok(Boolean::from(AllocatedBit::alloc(
+                                cs.namespace(|| format!("allocated bit {}", i)),
+                                v
+                            )?))
+                         })
+                         .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Ok(UInt64 {
+            bits: bits,
+            value: value
+        })
+    }
+
+    /// Turns this `UInt64` into its little-endian byte order representation.
+    pub fn into_bits(&self) -> Vec<Boolean> {
+        self.bits.chunks(8)
+                 .flat_map(|v| v.iter().rev())
+                 .cloned()
+                 .collect()
+    }
+
+    /// Converts a little-endian byte order representation of bits into a
+    /// `UInt64`.
+    pub fn from_bits(bits: &[Boolean]) -> Self
+    {
+        assert_eq!(bits.len(), 64);
+
+        let new_bits = bits.chunks(8)
+                           .flat_map(|v| v.iter().rev())
+                           .cloned()
+                           .collect::<Vec<_>>();
+
+        let mut value = Some(0u64);
+        for b in new_bits.iter().rev() {
+            value.as_mut().map(|v| *v <<= 1);
+
+            match b.get_value() {
+                Some(true) => { value.as_mut().map(|v| *v |= 1); },
+                Some(false) => {},
+                None => { value = None }
+            }
+        }
+
+        UInt64 {
+            value: value,
+            bits: new_bits
+        }
+    }
+
+    pub fn rotr(&self, by: usize) -> Self {
+        let by = by % 64;
+
+        let new_bits = self.bits.iter()
+                                .skip(by)
+                                .chain(self.bits.iter())
+                                .take(64)
+                                .cloned()
+                                .collect();
+
+        UInt64 {
+            bits: new_bits,
+            value: self.value.map(|v| v.rotate_right(by as u32))
+        }
+    }
+
+    /// XOR this `UInt64` with another `UInt64`
+    pub fn xor<E, CS>(
+        &self,
+        mut cs: CS,
+        other: &Self
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>
+    {
+        let new_value = match (self.value, other.value) {
+            (Some(a), Some(b)) => {
+                Some(a ^ b)
+            },
+            _ => None
+        };
+
+        let bits = self.bits.iter()
+                            .zip(other.bits.iter())
+                            .enumerate()
+                            .map(|(i, (a, b))| {
+                                Boolean::xor(
+                                    cs.namespace(|| format!("xor of bit {}", i)),
+                                    a,
+                                    b
+                                )
+                            })
+                            .collect::<Result<_, _>>()?;
+
+        Ok(UInt64 {
+            bits: bits,
+            value: new_value
+        })
+    }
+
+    /// Perform modular addition of several `UInt64` objects.
+    pub fn addmany<E, CS, M>(
+        mut cs: M,
+        operands: &[Self]
+    ) -> Result<Self, SynthesisError>
+        where E: Engine,
+              CS: ConstraintSystem<E>,
+              M: ConstraintSystem<E, Root=MultiEq<E, CS>>
+    {
+        // Make some arbitrary bounds for ourselves to avoid overflows
+        // in the scalar field
+        assert!(E::Fr::NUM_BITS >= 128);
+        assert!(operands.len() >= 2); // Weird trivial cases that should never happen
+        assert!(operands.len() <= 10);
+
+        // The maximum value of the sum is operands.len() * (2^64 - 1),
+        // which is (operands.len() - 1) * 2^64 + (2^64 - operands.len()),
+        // so the result needs 64 bits plus enough bits for the carry
+        let mut max_carry = (operands.len() as u64) - 1;
+        let mut num_bits = 64;
+        while max_carry != 0 {
+            num_bits += 1;
+            max_carry >>= 1;
+        }
+
+        // Keep track of the resulting value, as the low 64 bits
+        // and the carry
+        let mut result_value = Some((0u64, 0u64));
+
+        // This is a linear combination that we will enforce to equal the
+        // output
+        let mut lc = LinearCombination::zero();
+
+        let mut all_constants = true;
+
+        // Iterate over the operands
+        for op in operands {
+            // Accumulate the value
+            match op.value {
+                Some(val) => {
+                    result_value.as_mut().map(|v| {
+                        let (low, overflow) = v.0.overflowing_add(val);
+                        v.0 = low;
+                        if overflow {
+                            v.1 += 1;
+                        }
+                    });
+                },
+                None => {
+                    // If any of our operands have unknown value, we won't
+                    // know the value of the result
+                    result_value = None;
+                }
+            }
+
+            // Iterate over each bit of the operand and add the operand to
+            // the linear combination
+            let mut coeff = E::Fr::one();
+            for bit in &op.bits {
+                lc = lc + &bit.lc(CS::one(), coeff);
+
+                all_constants &= bit.is_constant();
+
+                coeff.double();
+            }
+        }
+
+        // The value of the actual result is modulo 2^64
+        let modular_value = result_value.map(|v| v.0);
+
+        if all_constants && modular_value.is_some() {
+            // We can just return a constant, rather than
+            // unpacking the result into allocated bits.
+
+            return Ok(UInt64::constant(modular_value.unwrap()));
+        }
+
+        // Storage area for the resulting bits
+        let mut result_bits = vec![];
+
+        // Linear combination representing the output,
+        // for comparison with the sum of the operands
+        let mut result_lc = LinearCombination::zero();
+
+        // Allocate each bit of the result
+        let mut coeff = E::Fr::one();
+        for i in 0..num_bits {
+            // Allocate the bit
+            let b = AllocatedBit::alloc(
+                cs.namespace(|| format!("result bit {}", i)),
+                result_value.map(|(low, carry)| {
+                    if i < 64 {
+                        (low >> i) & 1 == 1
+                    } else {
+                        (carry >> (i - 64)) & 1 == 1
+                    }
+                })
+            )?;
+
+            // Add this bit to the result combination
+            result_lc = result_lc + (coeff, b.get_variable());
+
+            result_bits.push(b.into());
+
+            coeff.double();
+        }
+
+        // Enforce equality between the sum and result
+        cs.get_root().enforce_equal(num_bits, &lc, &result_lc);
+
+        // Discard carry bits that we don't care about
+        result_bits.truncate(64);
+
+        Ok(UInt64 {
+            bits: result_bits,
+            value: modular_value
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{XorShiftRng, SeedableRng, Rng};
+    use ::circuit::boolean::{Boolean};
+    use super::{UInt64};
+    use pairing::bls12_381::{Bls12};
+    use pairing::{Field};
+    use ::circuit::test::*;
+    use bellman::{ConstraintSystem};
+    use circuit::multieq::MultiEq;
+
+    #[test]
+    fn test_uint64_from_bits() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let v = (0..64).map(|_| Boolean::constant(rng.gen())).collect::<Vec<_>>();
+
+            let b = UInt64::from_bits(&v);
+
+            for (i, bit) in b.bits.iter().enumerate() {
+                match bit {
+                    &Boolean::Constant(bit) => {
+                        assert!(bit == ((b.value.unwrap() >> i) & 1 == 1));
+                    },
+                    _ => unreachable!()
+                }
+            }
+
+            let expected_to_be_same = b.into_bits();
+
+            for x in v.iter().zip(expected_to_be_same.iter())
+            {
+                match x {
+                    (&Boolean::Constant(true), &Boolean::Constant(true)) => {},
+                    (&Boolean::Constant(false), &Boolean::Constant(false)) => {},
+                    _ => unreachable!()
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_xor() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0653]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let mut expected = a ^ b ^ c;
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::alloc(cs.namespace(|| "c_bit"), Some(c)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "first xor"), &b_bit).unwrap();
+            let r = r.xor(cs.namespace(|| "second xor"), &c_bit).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                match b {
+                    &Boolean::Is(ref b) => {
+                        assert!(b.get_value().unwrap() == (expected & 1 == 1));
+                    },
+                    &Boolean::Not(ref b) => {
+                        assert!(!b.get_value().unwrap() == (expected & 1 == 1));
+                    },
+                    &Boolean::Constant(b) => {
+                        assert!(b == (expected & 1 == 1));
+                    }
+                }
+
+                expected >>= 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_constants() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+
+            let a_bit = UInt64::constant(a);
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+
+            let mut expected = a.wrapping_add(b).wrapping_add(c);
+
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                let r = UInt64::addmany(cs.namespace(|| "addition"), &[a_bit, b_bit, c_bit]).unwrap();
+                r
+            };
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                match b {
+                    &Boolean::Is(_) => panic!(),
+                    &Boolean::Not(_) => panic!(),
+                    &Boolean::Constant(b) => {
+                        assert!(b == (expected & 1 == 1));
+                    }
+                }
+
+                expected >>= 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..1000 {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let a: u64 = rng.gen();
+            let b: u64 = rng.gen();
+            let c: u64 = rng.gen();
+            let d: u64 = rng.gen();
+
+            let mut expected = (a ^ b).wrapping_add(c).wrapping_add(d);
+
+            let a_bit = UInt64::alloc(cs.namespace(|| "a_bit"), Some(a)).unwrap();
+            let b_bit = UInt64::constant(b);
+            let c_bit = UInt64::constant(c);
+            let d_bit = UInt64::alloc(cs.namespace(|| "d_bit"), Some(d)).unwrap();
+
+            let r = a_bit.xor(cs.namespace(|| "xor"), &b_bit).unwrap();
+            let r = {
+                let mut cs = MultiEq::new(&mut cs);
+                let r = UInt64::addmany(cs.namespace(|| "addition"), &[r, c_bit, d_bit]).unwrap();
+                r
+            };
+
+            assert!(cs.is_satisfied());
+
+            assert!(r.value == Some(expected));
+
+            for b in r.bits.iter() {
+                match b {
+                    &Boolean::Is(ref b) => {
+                        assert!(b.get_value().unwrap() == (expected & 1 == 1));
+                    },
+                    &Boolean::Not(ref b) => {
+                        assert!(!b.get_value().unwrap() == (expected & 1 == 1));
+                    },
+                    &Boolean::Constant(_) => {
+                        unreachable!()
+                    }
+                }
+
+                expected >>= 1;
+            }
+
+            // Flip a bit and see if the addition constraint still works
+            if cs.get("addition/result bit 0/boolean").is_zero() {
+                cs.set("addition/result bit 0/boolean", Field::one());
+            } else {
+                cs.set("addition/result bit 0/boolean", Field::zero());
+            }
+
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_uint64_addmany_carry() {
+        // The carry of ten maximal operands needs four extra bits.
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let operands = (0..10).map(|i| {
+            UInt64::alloc(cs.namespace(|| format!("operand {}", i)), Some(u64::max_value())).unwrap()
+        }).collect::<Vec<_>>();
+
+        let r = {
+            let mut cs = MultiEq::new(&mut cs);
+            let r = UInt64::addmany(cs.namespace(|| "addition"), &operands).unwrap();
+            r
+        };
+
+        assert!(cs.is_satisfied());
+        assert!(r.value == Some(u64::max_value().wrapping_mul(10)));
+        assert!(cs.get("addition/result bit 67/boolean") == Field::one());
+    }
+
+    #[test]
+    fn test_uint64_rotr() {
+        let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut num = rng.gen();
+
+        let a = UInt64::constant(num);
+
+        for i in 0..64 {
+            let b = a.rotr(i);
+
+            assert!(b.value.unwrap() == num);
+
+            let mut tmp = num;
+            for b in &b.bits {
+                match b {
+                    &Boolean::Constant(b) => {
+                        assert_eq!(b, tmp & 1 == 1);
+                    },
+                    _ => unreachable!()
+                }
+
+                tmp >>= 1;
+            }
+
+            num = num.rotate_right(1);
+        }
+    }
+}
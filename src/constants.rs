@@ -9,6 +9,12 @@ pub const GH_FIRST_BLOCK: &'static [u8; 64] = b"0000000000000000002ffe76b973aaba
 pub const CRH_IVK_PERSONALIZATION: &'static [u8; 8] = b"Zcashivk";
 /// BLAKE2s Personalization for PRF^nr = BLAKE2s(rk | cm + position)
 pub const PRF_NR_PERSONALIZATION: &'static [u8; 8]  = b"WhatTheH";
+/// BLAKE2s Personalization for the diversifier key = BLAKE2s(ak | rk)
+pub const DIVERSIFIER_KEY_PERSONALIZATION: &'static [u8; 8] = b"Zcash_dk";
+/// BLAKE2s Personalization for diversifiers = BLAKE2s_dk(index)
+pub const DIVERSIFIER_PERSONALIZATION: &'static [u8; 8] = b"Zcash_dv";
+/// BLAKE2s Personalization for Poseidon round constants
+pub const POSEIDON_CONSTANTS_PERSONALIZATION: &'static [u8; 8] = b"Poseidon";
 
 // Group hash personalizations
 /// BLAKE2s Personalization for Pedersen hash generators.
@@ -27,3 +33,19 @@ pub const VALUE_COMMITMENT_VALUE_GENERATOR_PERSONALIZATION: &'static [u8; 8]
 pub const VALUE_COMMITMENT_RANDOMNESS_GENERATOR_PERSONALIZATION: &'static [u8; 8] = b"11111111";
 /// BLAKE2s Personalization for the spending key base point
 pub const SPENDING_KEY_GENERATOR_PERSONALIZATION: &'static [u8; 8] = b"sksksksk";
+
+// BLAKE2b invocation personalizations
+/// BLAKE2b Personalization for the hash H* used in RedJubjub signatures
+pub const REDJUBJUB_H_PERSONALIZATION: &'static [u8; 16] = b"Zcash_RedJubjubH";
+/// BLAKE2b Personalization for the note encryption KDF = BLAKE2b(dhsecret | epk)
+pub const NOTE_ENCRYPTION_KDF_PERSONALIZATION: &'static [u8; 16] = b"SaplingCrypt_KDF";
+/// BLAKE2b Personalization for the note encryption keystream
+pub const NOTE_ENCRYPTION_STREAM_PERSONALIZATION: &'static [u8; 16] = b"SaplingCrypt_Enc";
+/// BLAKE2b Personalization for the note encryption authentication tag
+pub const NOTE_ENCRYPTION_MAC_PERSONALIZATION: &'static [u8; 16] = b"SaplingCrypt_MAC";
+/// BLAKE2b Personalization for PRF^expand = BLAKE2b(sk | t)
+pub const PRF_EXPAND_PERSONALIZATION: &'static [u8; 16] = b"Zcash_ExpandSeed";
+/// BLAKE2b Personalization for the master extended spending key = BLAKE2b(seed)
+pub const SAPLING_MASTER_KEY_PERSONALIZATION: &'static [u8; 16] = b"ZcashIP32Sapling";
+/// BLAKE2b Personalization for full viewing key fingerprints = BLAKE2b(ak | rk | ovk)
+pub const SAPLING_FVK_FINGERPRINT_PERSONALIZATION: &'static [u8; 16] = b"ZcashSaplingFVFP";
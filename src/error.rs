@@ -0,0 +1,58 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Errors encountered when deriving or decoding Sapling objects.
+#[derive(Debug)]
+pub enum Error {
+    /// The diversifier does not hash to a valid point on the curve
+    InvalidDiversifier,
+    /// The encoding of a field element or point is not canonical
+    NonCanonicalEncoding,
+    /// The encoding does not correspond to a point on the curve
+    NotOnCurve,
+    /// The point is not in the prime order subgroup, or a hash to
+    /// the curve produced a point of small order
+    SmallOrderPoint,
+    /// A BLAKE2s personalization was not 8 bytes long
+    InvalidPersonalizationLength,
+    /// An error occurred while reading or writing
+    Io(io::Error)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidDiversifier => write!(f, "invalid diversifier"),
+            Error::NonCanonicalEncoding => write!(f, "non-canonical encoding"),
+            Error::NotOnCurve => write!(f, "point is not on the curve"),
+            Error::SmallOrderPoint => write!(f, "point is not in the prime order subgroup"),
+            Error::InvalidPersonalizationLength => write!(f, "personalization is not 8 bytes"),
+            Error::Io(ref e) => write!(f, "I/O error: {}", e)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e)
+        }
+    }
+}
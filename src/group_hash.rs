@@ -12,16 +12,22 @@ use pairing::{
 use blake2_rfc::blake2s::Blake2s;
 use constants;
 
+use error::Error;
+
 /// Produces a random point in the Jubjub curve.
 /// The point is guaranteed to be prime order
-/// and not the identity.
+/// and not the identity. Fails for roughly half
+/// of all tags, or if the personalization is not
+/// 8 bytes long.
 pub fn group_hash<E: JubjubEngine>(
     tag: &[u8],
     personalization: &[u8],
     params: &E::Params
-) -> Option<edwards::Point<E, PrimeOrder>>
+) -> Result<edwards::Point<E, PrimeOrder>, Error>
 {
-    assert_eq!(personalization.len(), 8);
+    if personalization.len() != 8 {
+        return Err(Error::InvalidPersonalizationLength);
+    }
 
     // Check to see that scalar field is 255 bits
     assert!(E::Fr::NUM_BITS == 255);
@@ -40,20 +46,51 @@ pub fn group_hash<E: JubjubEngine>(
     let mut y0 = <E::Fr as PrimeField>::Repr::default();
     y0.read_be(&h[..]).expect("hash is sufficiently large");
 
-    if let Ok(y0) = E::Fr::from_repr(y0) {
-        if let Some(p) = edwards::Point::<E, _>::get_for_y(y0, s, params) {
-            // Enter into the prime order subgroup
-            let p = p.mul_by_cofactor(params);
+    let y0 = E::Fr::from_repr(y0).map_err(|_| Error::NonCanonicalEncoding)?;
+    let p = edwards::Point::<E, _>::get_for_y(y0, s, params).ok_or(Error::NotOnCurve)?;
+
+    // Enter into the prime order subgroup
+    let p = p.mul_by_cofactor(params);
+
+    if p != edwards::Point::zero() {
+        Ok(p)
+    } else {
+        Err(Error::SmallOrderPoint)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pairing::bls12_381::Bls12;
+    use jubjub::JubjubBls12;
+    use super::*;
+
+    #[test]
+    fn test_group_hash_errors() {
+        let params = &JubjubBls12::new();
+
+        match group_hash::<Bls12>(&[0], b"1234567", params) {
+            Err(Error::InvalidPersonalizationLength) => {},
+            _ => panic!("short personalizations should be rejected")
+        }
 
-            if p != edwards::Point::zero() {
-                Some(p)
-            } else {
-                None
+        match group_hash::<Bls12>(&[0], b"123456789", params) {
+            Err(Error::InvalidPersonalizationLength) => {},
+            _ => panic!("long personalizations should be rejected")
+        }
+
+        // About half of all tags fail to hash to the curve.
+        let mut successes = 0;
+        for i in 0..64u8 {
+            match group_hash::<Bls12>(&[i], constants::KEY_DIVERSIFICATION_PERSONALIZATION, params) {
+                Ok(p) => {
+                    assert!(p != edwards::Point::zero());
+                    successes += 1;
+                },
+                Err(Error::NotOnCurve) | Err(Error::NonCanonicalEncoding) => {},
+                Err(e) => panic!("unexpected error: {}", e)
             }
-        } else {
-            None
         }
-    } else {
-        None
+        assert!(successes > 0 && successes < 64);
     }
 }
@@ -9,6 +9,7 @@ use pairing::{
 use super::{
     JubjubEngine,
     JubjubParams,
+    FixedGenerators,
     Unknown,
     PrimeOrder,
     montgomery
@@ -20,6 +21,8 @@ use rand::{
 
 use std::marker::PhantomData;
 
+use error::Error;
+
 use std::io::{
     self,
     Write,
@@ -89,6 +92,83 @@ impl<E: JubjubEngine, Subgroup> PartialEq for Point<E, Subgroup> {
     }
 }
 
+/// An affine point (x, y), together with t = xy so that it can be
+/// added to a point in extended coordinates without converting it.
+/// Obtained from `Point::into_affine` or, for many points with a
+/// single inversion, `Point::batch_normalize`.
+pub struct AffinePoint<E: JubjubEngine, Subgroup> {
+    x: E::Fr,
+    y: E::Fr,
+    t: E::Fr,
+    _marker: PhantomData<Subgroup>
+}
+
+impl<E: JubjubEngine> From<AffinePoint<E, PrimeOrder>> for AffinePoint<E, Unknown>
+{
+    fn from(p: AffinePoint<E, PrimeOrder>) -> AffinePoint<E, Unknown>
+    {
+        p.convert_subgroup()
+    }
+}
+
+impl<E: JubjubEngine, Subgroup> Clone for AffinePoint<E, Subgroup>
+{
+    fn clone(&self) -> Self {
+        self.convert_subgroup()
+    }
+}
+
+impl<E: JubjubEngine, Subgroup> PartialEq for AffinePoint<E, Subgroup> {
+    fn eq(&self, other: &AffinePoint<E, Subgroup>) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+impl<E: JubjubEngine, Subgroup> AffinePoint<E, Subgroup> {
+    fn convert_subgroup<S>(&self) -> AffinePoint<E, S> {
+        AffinePoint {
+            x: self.x,
+            y: self.y,
+            t: self.t,
+            _marker: PhantomData
+        }
+    }
+
+    pub fn zero() -> Self {
+        AffinePoint {
+            x: E::Fr::zero(),
+            y: E::Fr::one(),
+            t: E::Fr::zero(),
+            _marker: PhantomData
+        }
+    }
+
+    pub fn into_xy(&self) -> (E::Fr, E::Fr)
+    {
+        (self.x, self.y)
+    }
+
+    pub fn into_projective(&self) -> Point<E, Subgroup>
+    {
+        Point {
+            x: self.x,
+            y: self.y,
+            t: self.t,
+            z: E::Fr::one(),
+            _marker: PhantomData
+        }
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut p = self.clone();
+
+        p.x.negate();
+        p.t.negate();
+
+        p
+    }
+}
+
 fn swap_bits_u64(x: u64) -> u64
 {
     let mut tmp = 0;
@@ -121,7 +201,7 @@ impl<E: JubjubEngine> Point<E, Unknown> {
     pub fn read<R: Read>(
         reader: R,
         params: &E::Params
-    ) -> io::Result<Self>
+    ) -> Result<Self, Error>
     {
         // Jubjub points are encoded least significant bit first.
         // The most significant bit (bit 254) encodes the parity
@@ -142,19 +222,16 @@ impl<E: JubjubEngine> Point<E, Unknown> {
         let x_sign = (y_repr.as_ref()[3] >> 63) == 1;
         y_repr.as_mut()[3] &= 0x7fffffffffffffff;
 
-        match E::Fr::from_repr(y_repr) {
-            Ok(y) => {
-                match Self::get_for_y(y, x_sign, params) {
-                    Some(p) => Ok(p),
-                    None => {
-                        Err(io::Error::new(io::ErrorKind::InvalidInput, "not on curve"))
-                    }
-                }
-            },
-            Err(_) => {
-                Err(io::Error::new(io::ErrorKind::InvalidInput, "y is not in field"))
-            }
+        let y = E::Fr::from_repr(y_repr).map_err(|_| Error::NonCanonicalEncoding)?;
+        let p = Self::get_for_y(y, x_sign, params).ok_or(Error::NotOnCurve)?;
+
+        // The sign of x = 0 is not determined by the
+        // point, so only the unset bit is canonical.
+        if x_sign && p.x.is_zero() {
+            return Err(Error::NonCanonicalEncoding);
         }
+
+        Ok(p)
     }
 
     pub fn get_for_y(y: E::Fr, sign: bool, params: &E::Params) -> Option<Self>
@@ -393,6 +470,69 @@ impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
         (x, y)
     }
 
+    /// Converts this point to affine coordinates, which costs an
+    /// inversion.
+    pub fn into_affine(&self) -> AffinePoint<E, Subgroup>
+    {
+        let (x, y) = self.into_xy();
+
+        let mut t = x;
+        t.mul_assign(&y);
+
+        AffinePoint {
+            x: x,
+            y: y,
+            t: t,
+            _marker: PhantomData
+        }
+    }
+
+    /// Converts many points to affine coordinates at the cost of a
+    /// single inversion, using Montgomery's trick.
+    pub fn batch_normalize(points: &[Self]) -> Vec<AffinePoint<E, Subgroup>>
+    {
+        // The z-coordinate of a point on the curve is never zero,
+        // as the addition law is complete.
+
+        // prods[i] = z_0 * ... * z_{i-1}
+        let mut prods = Vec::with_capacity(points.len());
+        let mut acc = E::Fr::one();
+        for p in points {
+            prods.push(acc);
+            acc.mul_assign(&p.z);
+        }
+
+        // acc = 1 / (z_0 * ... * z_{n-1})
+        acc = acc.inverse().unwrap();
+
+        let mut res = Vec::with_capacity(points.len());
+        for (p, prod) in points.iter().zip(prods.into_iter()).rev() {
+            // zinv = 1 / z_i
+            let mut zinv = acc;
+            zinv.mul_assign(&prod);
+            acc.mul_assign(&p.z);
+
+            let mut x = p.x;
+            x.mul_assign(&zinv);
+
+            let mut y = p.y;
+            y.mul_assign(&zinv);
+
+            let mut t = x;
+            t.mul_assign(&y);
+
+            res.push(AffinePoint {
+                x: x,
+                y: y,
+                t: t,
+                _marker: PhantomData
+            });
+        }
+        res.reverse();
+
+        res
+    }
+
     pub fn negate(&self) -> Self {
         let mut p = self.clone();
 
@@ -482,6 +622,75 @@ impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
         }
     }
 
+    /// Adds an affine point, which saves a multiplication over
+    /// `add` as its z-coordinate is one.
+    pub fn add_mixed(&self, other: &AffinePoint<E, Subgroup>, params: &E::Params) -> Self
+    {
+        // See `add`, with z2 = 1.
+
+        // A = x1 * x2
+        let mut a = self.x;
+        a.mul_assign(&other.x);
+
+        // B = y1 * y2
+        let mut b = self.y;
+        b.mul_assign(&other.y);
+
+        // C = d * t1 * t2
+        let mut c = params.edwards_d().clone();
+        c.mul_assign(&self.t);
+        c.mul_assign(&other.t);
+
+        // D = z1
+        let d = self.z;
+
+        // H = B + A
+        let mut h = b;
+        h.add_assign(&a);
+
+        // E = (x1 + y1) * (x2 + y2) - H
+        let mut e = self.x;
+        e.add_assign(&self.y);
+        {
+            let mut tmp = other.x;
+            tmp.add_assign(&other.y);
+            e.mul_assign(&tmp);
+        }
+        e.sub_assign(&h);
+
+        // F = D - C
+        let mut f = d;
+        f.sub_assign(&c);
+
+        // G = D + C
+        let mut g = d;
+        g.add_assign(&c);
+
+        // x3 = E * F
+        let mut x3 = e;
+        x3.mul_assign(&f);
+
+        // y3 = G * H
+        let mut y3 = g;
+        y3.mul_assign(&h);
+
+        // t3 = E * H
+        let mut t3 = e;
+        t3.mul_assign(&h);
+
+        // z3 = F * G
+        let mut z3 = f;
+        z3.mul_assign(&g);
+
+        Point {
+            x: x3,
+            y: y3,
+            t: t3,
+            z: z3,
+            _marker: PhantomData
+        }
+    }
+
     pub fn mul<S: Into<<E::Fs as PrimeField>::Repr>>(
         &self,
         scalar: S,
@@ -502,4 +711,233 @@ impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
 
         res
     }
+
+    /// Scalar multiplication using the width-4 non-adjacent form of
+    /// the scalar, which needs far fewer additions than `mul` for
+    /// the cost of precomputing the odd multiples of the base. The
+    /// scalar must be less than 2^255, as elements of the scalar
+    /// field are.
+    ///
+    /// The additions and table lookups depend on the scalar, so this
+    /// must only be used with public scalars; use `mul_secret` for
+    /// secrets.
+    pub fn mul_wnaf<S: Into<<E::Fs as PrimeField>::Repr>>(
+        &self,
+        scalar: S,
+        params: &E::Params
+    ) -> Self
+    {
+        // table = [P, 3P, 5P, 7P]
+        let mut table = Vec::with_capacity(1 << (WNAF_WINDOW - 2));
+        {
+            let double = self.double(params);
+            let mut cur = self.clone();
+            for _ in 0..(1 << (WNAF_WINDOW - 2)) {
+                let next = cur.add(&double, params);
+                table.push(cur);
+                cur = next;
+            }
+        }
+
+        let mut res = Self::zero();
+
+        for digit in wnaf::<E>(scalar.into(), WNAF_WINDOW).into_iter().rev() {
+            res = res.double(params);
+
+            if digit > 0 {
+                res = res.add(&table[(digit / 2) as usize], params);
+            } else if digit < 0 {
+                res = res.add(&table[(-digit / 2) as usize].negate(), params);
+            }
+        }
+
+        res
+    }
+}
+
+impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
+    /// Scalar multiplication for secret scalars, using a Montgomery
+    /// ladder over every bit of the scalar representation. The
+    /// sequence of operations does not depend on the scalar, and the
+    /// ladder state is swapped arithmetically rather than by
+    /// branching.
+    ///
+    /// This is not constant time: the arithmetic of `E::Fr` may
+    /// branch on the values it operates on, and for `Bls12` it does.
+    pub fn mul_secret<S: Into<<E::Fs as PrimeField>::Repr>>(
+        &self,
+        scalar: S,
+        params: &E::Params
+    ) -> Self
+    {
+        // Invariant: r1 = r0 + self
+        let mut r0 = Self::zero();
+        let mut r1 = self.clone();
+
+        for b in BitIterator::new(scalar.into()) {
+            let choice = choice_to_field::<E::Fr>(b as u64);
+
+            // (r0, r1) = (2 r0, r0 + r1) if b is unset,
+            //            (r0 + r1, 2 r1) if b is set.
+            conditional_swap(&mut r0, &mut r1, &choice);
+            r1 = r0.add(&r1, params);
+            r0 = r0.double(params);
+            conditional_swap(&mut r0, &mut r1, &choice);
+        }
+
+        r0
+    }
+}
+
+impl<E: JubjubEngine> Point<E, PrimeOrder> {
+    /// Multiplies one of the fixed generators by `scalar`, adding
+    /// together one precomputed multiple from each window of
+    /// `params.generator_table(base)`.
+    ///
+    /// The table lookups depend on the scalar, so this must only be
+    /// used with public scalars; use `fixed_base_mul_secret` for secrets.
+    pub fn fixed_base_mul<S: Into<<E::Fs as PrimeField>::Repr>>(
+        base: FixedGenerators,
+        scalar: S,
+        params: &E::Params
+    ) -> Self
+    {
+        let scalar = scalar.into();
+        let windows_per_limb = 64 / FIXED_BASE_WINDOW;
+
+        let mut res = Self::zero();
+
+        for (i, window) in params.generator_table(base).iter().enumerate() {
+            let limb = scalar.as_ref()[i / windows_per_limb];
+            let digit = (limb >> ((i % windows_per_limb) * FIXED_BASE_WINDOW)) & ((1 << FIXED_BASE_WINDOW) - 1);
+
+            res = res.add_mixed(&window[digit as usize], params);
+        }
+
+        res
+    }
+}
+
+impl<E: JubjubEngine> Point<E, PrimeOrder> {
+    /// Multiplies one of the fixed generators by a secret `scalar`.
+    /// This is `fixed_base_mul`, except that every entry of each
+    /// window is read and the one for the scalar's digit is
+    /// selected arithmetically, so that neither the operations nor
+    /// the memory accesses depend on the scalar. As with
+    /// `mul_secret`, the arithmetic of `E::Fr` may still branch on
+    /// values.
+    pub fn fixed_base_mul_secret<S: Into<<E::Fs as PrimeField>::Repr>>(
+        base: FixedGenerators,
+        scalar: S,
+        params: &E::Params
+    ) -> Self
+    {
+        let scalar = scalar.into();
+        let windows_per_limb = 64 / FIXED_BASE_WINDOW;
+
+        let mut res = Self::zero();
+
+        for (i, window) in params.generator_table(base).iter().enumerate() {
+            let limb = scalar.as_ref()[i / windows_per_limb];
+            let digit = (limb >> ((i % windows_per_limb) * FIXED_BASE_WINDOW)) & ((1 << FIXED_BASE_WINDOW) - 1);
+
+            let mut selected = AffinePoint::zero();
+            for (j, entry) in window.iter().enumerate() {
+                // The top bit of (digit ^ j) - 1 is set only if digit == j.
+                let choice = choice_to_field::<E::Fr>((digit ^ (j as u64)).wrapping_sub(1) >> 63);
+
+                selected.x = conditional_select(&selected.x, &entry.x, &choice);
+                selected.y = conditional_select(&selected.y, &entry.y, &choice);
+                selected.t = conditional_select(&selected.t, &entry.t, &choice);
+            }
+
+            res = res.add_mixed(&selected, params);
+        }
+
+        res
+    }
+}
+
+/// Maps a choice bit, which must be 0 or 1, to the same element of
+/// the field.
+fn choice_to_field<F: PrimeField>(choice: u64) -> F {
+    F::from_repr(F::Repr::from(choice)).unwrap()
+}
+
+/// Returns `a` if `choice` is zero and `b` if it is one, as
+/// a + choice * (b - a).
+fn conditional_select<F: PrimeField>(a: &F, b: &F, choice: &F) -> F {
+    let mut res = *b;
+    res.sub_assign(a);
+    res.mul_assign(choice);
+    res.add_assign(a);
+
+    res
+}
+
+/// Swaps `a` and `b` if `choice` is one, and leaves them if it is
+/// zero.
+fn conditional_swap<E: JubjubEngine, Subgroup>(
+    a: &mut Point<E, Subgroup>,
+    b: &mut Point<E, Subgroup>,
+    choice: &E::Fr
+)
+{
+    conditional_swap_field(&mut a.x, &mut b.x, choice);
+    conditional_swap_field(&mut a.y, &mut b.y, choice);
+    conditional_swap_field(&mut a.t, &mut b.t, choice);
+    conditional_swap_field(&mut a.z, &mut b.z, choice);
+}
+
+fn conditional_swap_field<F: PrimeField>(a: &mut F, b: &mut F, choice: &F) {
+    // delta = choice * (b - a)
+    let mut delta = *b;
+    delta.sub_assign(a);
+    delta.mul_assign(choice);
+
+    a.add_assign(&delta);
+    b.sub_assign(&delta);
+}
+
+/// The number of scalar bits in each window of the fixed-base
+/// tables returned by `JubjubParams::generator_table`.
+pub const FIXED_BASE_WINDOW: usize = 4;
+
+/// The width of the non-adjacent form used by `Point::mul_wnaf`.
+const WNAF_WINDOW: u32 = 4;
+
+/// Computes the width-`w` non-adjacent form of `e`, least
+/// significant digit first. Every nonzero digit is odd and less
+/// than 2^(w-1) in magnitude, and is followed by at least `w - 1`
+/// zero digits.
+fn wnaf<E: JubjubEngine>(
+    mut e: <E::Fs as PrimeField>::Repr,
+    w: u32
+) -> Vec<i64>
+{
+    let mut res = vec![];
+
+    while !e.is_zero() {
+        let digit = if e.is_odd() {
+            let mut z = (e.as_ref()[0] % (1 << w)) as i64;
+            if z > (1 << (w - 1)) {
+                z -= 1 << w;
+            }
+
+            if z > 0 {
+                e.sub_noborrow(&<E::Fs as PrimeField>::Repr::from(z as u64));
+            } else {
+                e.add_nocarry(&<E::Fs as PrimeField>::Repr::from((-z) as u64));
+            }
+
+            z
+        } else {
+            0
+        };
+
+        res.push(digit);
+        e.shr(1);
+    }
+
+    res
 }
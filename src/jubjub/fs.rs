@@ -224,6 +224,18 @@ impl PrimeFieldRepr for FsRepr {
     }
 }
 
+/// Returns `a` if `mask` is zero and `b` if it is all ones.
+#[inline(always)]
+fn select_repr(a: &FsRepr, b: &FsRepr, mask: u64) -> FsRepr {
+    let mut res = *a;
+
+    for (r, b) in res.0.iter_mut().zip(b.0.iter()) {
+        *r ^= mask & (*r ^ *b);
+    }
+
+    res
+}
+
 /// This is an element of the scalar field of the Jubjub curve.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Fs(FsRepr);
@@ -333,21 +345,30 @@ impl Field for Fs {
 
     #[inline]
     fn sub_assign(&mut self, other: &Fs) {
-        // If `other` is larger than `self`, we'll need to add the modulus to self first.
-        if other.0 > self.0 {
-            self.0.add_nocarry(&MODULUS);
+        let mut borrow = 0;
+
+        for (a, b) in (self.0).0.iter_mut().zip((other.0).0.iter()) {
+            *a = sbb(*a, *b, &mut borrow);
         }
 
-        self.0.sub_noborrow(&other.0);
+        // If `other` was larger than `self`, we need to add the modulus
+        // back, which we do without branching on the values.
+        let mask = 0u64.wrapping_sub(borrow);
+        let mut carry = 0;
+
+        for (a, b) in (self.0).0.iter_mut().zip(MODULUS.0.iter()) {
+            *a = adc(*a, *b & mask, &mut carry);
+        }
     }
 
     #[inline]
     fn negate(&mut self) {
-        if !self.is_zero() {
-            let mut tmp = MODULUS;
-            tmp.sub_noborrow(&self.0);
-            self.0 = tmp;
-        }
+        let mut tmp = MODULUS;
+        tmp.sub_noborrow(&self.0);
+        self.0 = tmp;
+
+        // The negation of zero is the modulus itself.
+        self.reduce();
     }
 
     fn inverse(&self) -> Option<Self> {
@@ -486,12 +507,42 @@ impl Fs {
     }
 
     /// Subtracts the modulus from this element if this element is not in the
-    /// field. Only used internally.
+    /// field, without branching on its value. Only used internally.
     #[inline(always)]
     fn reduce(&mut self) {
-        if !self.is_valid() {
-            self.0.sub_noborrow(&MODULUS);
+        let mut tmp = self.0;
+        let mut borrow = 0;
+
+        for (a, b) in tmp.0.iter_mut().zip(MODULUS.0.iter()) {
+            *a = sbb(*a, *b, &mut borrow);
+        }
+
+        // Keep this element if the subtraction borrowed.
+        self.0 = select_repr(&tmp, &self.0, 0u64.wrapping_sub(borrow));
+    }
+
+    /// Returns `a` if `choice` is false and `b` if it is true, in
+    /// constant time.
+    pub fn conditional_select(a: &Fs, b: &Fs, choice: bool) -> Fs {
+        Fs(select_repr(&a.0, &b.0, 0u64.wrapping_sub(choice as u64)))
+    }
+
+    /// Determines if two elements are equal, in constant time.
+    pub fn ct_eq(&self, other: &Fs) -> bool {
+        let mut diff = 0u64;
+
+        for (a, b) in (self.0).0.iter().zip((other.0).0.iter()) {
+            diff |= a ^ b;
         }
+
+        // The top bit of diff | -diff is set only if diff is nonzero.
+        (diff | diff.wrapping_neg()) >> 63 == 0
+    }
+
+    /// Computes the inverse as self^(s - 2), which takes the same time
+    /// for every element, unlike `inverse`. Zero is mapped to zero.
+    pub fn inverse_ct(&self) -> Fs {
+        self.pow([0xd0970e5ed6f72cb5, 0xa6682093ccc81082, 0x6673b0101343b00, 0xe7db4ea6533afa9])
     }
 
     #[inline(always)]
@@ -1025,6 +1076,47 @@ fn test_fs_inverse() {
     }
 }
 
+#[test]
+fn test_fs_constant_time_ops() {
+    let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    assert_eq!(Fs::zero().inverse_ct(), Fs::zero());
+    assert_eq!(Fs::one().inverse_ct(), Fs::one());
+
+    let mut minus_one = Fs::one();
+    minus_one.negate();
+    assert_eq!(minus_one, NEGATIVE_ONE);
+    assert_eq!(minus_one.inverse_ct(), minus_one);
+
+    let mut zero = Fs::zero();
+    zero.negate();
+    assert_eq!(zero, Fs::zero());
+
+    for _ in 0..1000 {
+        let a = Fs::rand(&mut rng);
+        let b = Fs::rand(&mut rng);
+
+        assert_eq!(a.inverse_ct(), a.inverse().unwrap());
+
+        assert_eq!(Fs::conditional_select(&a, &b, false), a);
+        assert_eq!(Fs::conditional_select(&a, &b, true), b);
+
+        assert!(a.ct_eq(&a));
+        assert_eq!(a.ct_eq(&b), a == b);
+
+        // Elements differing in a single bit are not equal.
+        let mut c = a;
+        (c.0).0[3] ^= 1 << 10;
+        assert!(!a.ct_eq(&c));
+
+        // Subtraction wraps around in both directions.
+        let mut d = a;
+        d.sub_assign(&b);
+        d.add_assign(&b);
+        assert_eq!(d, a);
+    }
+}
+
 #[test]
 fn test_fs_double() {
     let mut rng = XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
@@ -43,6 +43,10 @@ pub mod montgomery;
 /// This is an implementation of the scalar field for Jubjub.
 pub mod fs;
 
+/// This is an implementation of multi-scalar multiplication
+/// over the twisted Edwards curve.
+pub mod multiexp;
+
 #[cfg(test)]
 pub mod tests;
 
@@ -113,6 +117,10 @@ pub trait JubjubParams<E: JubjubEngine>: Sized {
     /// Returns the pre-computed window tables [-4, 3, 2, 1, 1, 2, 3, 4] of different
     /// magnitudes of the Pedersen hash segment generators.
     fn pedersen_circuit_generators(&self) -> &[Vec<Vec<(E::Fr, E::Fr)>>];
+    /// Returns the pre-computed window tables [1, 2, 3, 4] of different magnitudes
+    /// of the Pedersen hash segment generators in affine form, for the native
+    /// Pedersen hash.
+    fn pedersen_hash_exp_table(&self) -> &[Vec<Vec<edwards::AffinePoint<E, PrimeOrder>>>];
 
     /// Returns the number of chunks needed to represent a full scalar during fixed-base
     /// exponentiation.
@@ -122,6 +130,10 @@ pub trait JubjubParams<E: JubjubEngine>: Sized {
     /// Returns a window table [0, 1, ..., 8] for different magnitudes of some
     /// fixed generator.
     fn circuit_generators(&self, FixedGenerators) -> &[Vec<(E::Fr, E::Fr)>];
+    /// Returns the pre-computed window tables [0, 1, ..., 15] of multiples of
+    /// some fixed generator in affine form, for each 4-bit window of a scalar,
+    /// used for native fixed-base exponentiation.
+    fn generator_table(&self, FixedGenerators) -> &[Vec<edwards::AffinePoint<E, PrimeOrder>>];
 }
 
 impl JubjubEngine for Bls12 {
@@ -137,9 +149,11 @@ pub struct JubjubBls12 {
 
     pedersen_hash_generators: Vec<edwards::Point<Bls12, PrimeOrder>>,
     pedersen_circuit_generators: Vec<Vec<Vec<(Fr, Fr)>>>,
+    pedersen_hash_exp_table: Vec<Vec<Vec<edwards::AffinePoint<Bls12, PrimeOrder>>>>,
 
     fixed_base_generators: Vec<edwards::Point<Bls12, PrimeOrder>>,
     fixed_base_circuit_generators: Vec<Vec<Vec<(Fr, Fr)>>>,
+    fixed_base_tables: Vec<Vec<Vec<edwards::AffinePoint<Bls12, PrimeOrder>>>>,
 }
 
 impl JubjubParams<Bls12> for JubjubBls12 {
@@ -159,6 +173,9 @@ impl JubjubParams<Bls12> for JubjubBls12 {
     fn pedersen_circuit_generators(&self) -> &[Vec<Vec<(Fr, Fr)>>] {
         &self.pedersen_circuit_generators
     }
+    fn pedersen_hash_exp_table(&self) -> &[Vec<Vec<edwards::AffinePoint<Bls12, PrimeOrder>>>] {
+        &self.pedersen_hash_exp_table
+    }
     fn generator(&self, base: FixedGenerators) -> &edwards::Point<Bls12, PrimeOrder>
     {
         &self.fixed_base_generators[base as usize]
@@ -167,6 +184,10 @@ impl JubjubParams<Bls12> for JubjubBls12 {
     {
         &self.fixed_base_circuit_generators[base as usize][..]
     }
+    fn generator_table(&self, base: FixedGenerators) -> &[Vec<edwards::AffinePoint<Bls12, PrimeOrder>>]
+    {
+        &self.fixed_base_tables[base as usize][..]
+    }
 }
 
 impl JubjubBls12 {
@@ -188,8 +209,10 @@ impl JubjubBls12 {
             // We'll initialize these below
             pedersen_hash_generators: vec![],
             pedersen_circuit_generators: vec![],
+            pedersen_hash_exp_table: vec![],
             fixed_base_generators: vec![],
             fixed_base_circuit_generators: vec![],
+            fixed_base_tables: vec![],
         };
 
         // Create the bases for the Pedersen hashes
@@ -207,7 +230,7 @@ impl JubjubBls12 {
                 assert!(cur != u8::max_value());
                 cur += 1;
 
-                if let Some(gh) = gh {
+                if let Ok(gh) = gh {
                     pedersen_hash_generators.push(gh);
                 }
             }
@@ -244,7 +267,7 @@ impl JubjubBls12 {
                         assert!(cur != u8::max_value());
                         cur += 1;
 
-                        if let Some(gh) = gh {
+                        if let Ok(gh) = gh {
                             break gh;
                         }
                     }
@@ -327,6 +350,34 @@ impl JubjubBls12 {
             tmp_params.pedersen_circuit_generators = pedersen_circuit_generators;
         }
 
+        // Create the native window tables for each 4-bit
+        // "chunk" in each segment of the Pedersen hash
+        {
+            let mut pedersen_hash_exp_table = vec![];
+
+            for mut gen in tmp_params.pedersen_hash_generators.iter().cloned() {
+                let mut windows = vec![];
+                for _ in 0..tmp_params.pedersen_hash_chunks_per_generator() {
+                    // coeffs = g, g*2, g*3, g*4
+                    let mut coeffs = vec![];
+                    let mut g = gen.clone();
+                    for _ in 0..4 {
+                        coeffs.push(g.clone());
+                        g = g.add(&gen, &tmp_params);
+                    }
+                    windows.push(edwards::Point::batch_normalize(&coeffs));
+
+                    // Our chunks are separated by 2 bits to prevent overlap.
+                    for _ in 0..4 {
+                        gen = gen.double(&tmp_params);
+                    }
+                }
+                pedersen_hash_exp_table.push(windows);
+            }
+
+            tmp_params.pedersen_hash_exp_table = pedersen_hash_exp_table;
+        }
+
         // Create the 3-bit window table lookups for fixed-base
         // exp of each base in the protocol.
         {
@@ -352,6 +403,32 @@ impl JubjubBls12 {
             tmp_params.fixed_base_circuit_generators = fixed_base_circuit_generators;
         }
 
+        // Create the 4-bit window tables for native fixed-base
+        // exp of each base in the protocol, covering every bit
+        // of a scalar representation.
+        {
+            let mut fixed_base_tables = vec![];
+
+            for mut gen in tmp_params.fixed_base_generators.iter().cloned() {
+                let mut windows = vec![];
+                for _ in 0..(256 / edwards::FIXED_BASE_WINDOW) {
+                    let mut coeffs = vec![edwards::Point::zero()];
+                    let mut g = gen.clone();
+                    for _ in 1..(1 << edwards::FIXED_BASE_WINDOW) {
+                        coeffs.push(g.clone());
+                        g = g.add(&gen, &tmp_params);
+                    }
+                    windows.push(edwards::Point::batch_normalize(&coeffs));
+
+                    // gen = gen * 16
+                    gen = g;
+                }
+                fixed_base_tables.push(windows);
+            }
+
+            tmp_params.fixed_base_tables = fixed_base_tables;
+        }
+
         tmp_params
     }
 }
@@ -372,3 +449,14 @@ fn test_jubjub_bls12() {
 
     assert!(p == q);
 }
+
+// Run with `cargo test --release -- --ignored` on an idle machine.
+// The field arithmetic of Bls12 branches on its operands, so this
+// only checks for leaks in the ladder itself, not constant time.
+#[test]
+#[ignore]
+fn test_jubjub_bls12_secret_timing() {
+    let params = JubjubBls12::new();
+
+    tests::test_secret_timing::<Bls12>(&params, 5000);
+}
@@ -0,0 +1,223 @@
+//! Multi-scalar multiplication, computing sums of many scalar
+//! products of Jubjub points with Pippenger's bucket method.
+
+use pairing::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use crossbeam;
+use num_cpus;
+
+use std::cmp;
+
+use super::{
+    JubjubEngine,
+    edwards
+};
+
+/// Returns the window size to use for `n` bases.
+fn window_size(n: usize) -> usize {
+    if n < 32 {
+        3
+    } else {
+        (f64::from(n as u32)).ln().ceil() as usize
+    }
+}
+
+/// Returns the `c` bits of `repr` starting at bit `skip`.
+fn get_window<R: PrimeFieldRepr>(repr: &R, skip: usize, c: usize) -> usize {
+    let limbs = repr.as_ref();
+    let limb = skip / 64;
+    let shift = skip % 64;
+
+    if limb >= limbs.len() {
+        return 0;
+    }
+
+    let mut window = limbs[limb] >> shift;
+    if shift + c > 64 && limb + 1 < limbs.len() {
+        window |= limbs[limb + 1] << (64 - shift);
+    }
+
+    (window & ((1 << c) - 1)) as usize
+}
+
+/// Computes the sum of the bases weighted by the `c` bits of their
+/// scalars starting at bit `skip`.
+fn window_sum<E: JubjubEngine, Subgroup>(
+    bases: &[edwards::Point<E, Subgroup>],
+    scalars: &[<E::Fs as PrimeField>::Repr],
+    skip: usize,
+    c: usize,
+    params: &E::Params
+) -> edwards::Point<E, Subgroup>
+{
+    // buckets[i] is the sum of the bases whose window is i + 1
+    let mut buckets = vec![edwards::Point::zero(); (1 << c) - 1];
+
+    for (base, scalar) in bases.iter().zip(scalars.iter()) {
+        let window = get_window(scalar, skip, c);
+
+        if window != 0 {
+            buckets[window - 1] = buckets[window - 1].add(base, params);
+        }
+    }
+
+    // Summing the running sums of the buckets from the top
+    // weights each bucket by its window.
+    let mut running_sum = edwards::Point::zero();
+    let mut acc = edwards::Point::zero();
+    for bucket in buckets.iter().rev() {
+        running_sum = running_sum.add(bucket, params);
+        acc = acc.add(&running_sum, params);
+    }
+
+    acc
+}
+
+/// Combines the window sums, most significant first.
+fn combine<E: JubjubEngine, Subgroup>(
+    window_sums: Vec<edwards::Point<E, Subgroup>>,
+    c: usize,
+    params: &E::Params
+) -> edwards::Point<E, Subgroup>
+{
+    let mut res = edwards::Point::zero();
+
+    for window_sum in window_sums.into_iter().rev() {
+        for _ in 0..c {
+            res = res.double(params);
+        }
+
+        res = res.add(&window_sum, params);
+    }
+
+    res
+}
+
+/// Returns the number of windows of size `c` needed to cover the
+/// largest scalar.
+fn num_windows<R: PrimeFieldRepr>(scalars: &[R], c: usize) -> usize {
+    let num_bits = scalars.iter().map(|s| s.num_bits() as usize).max().unwrap_or(0);
+
+    (num_bits + c - 1) / c
+}
+
+/// Computes the sum of `bases[i] * scalars[i]`.
+pub fn multiexp<E: JubjubEngine, Subgroup>(
+    bases: &[edwards::Point<E, Subgroup>],
+    scalars: &[<E::Fs as PrimeField>::Repr],
+    params: &E::Params
+) -> edwards::Point<E, Subgroup>
+{
+    assert_eq!(bases.len(), scalars.len());
+
+    let c = window_size(bases.len());
+
+    let window_sums = (0..num_windows(scalars, c)).map(|i| {
+        window_sum(bases, scalars, i * c, c, params)
+    }).collect();
+
+    combine(window_sums, c, params)
+}
+
+/// Computes the sum of `bases[i] * scalars[i]` as `multiexp` does,
+/// computing the window sums in chunks, one per CPU.
+pub fn multiexp_parallel<E: JubjubEngine, Subgroup>(
+    bases: &[edwards::Point<E, Subgroup>],
+    scalars: &[<E::Fs as PrimeField>::Repr],
+    params: &E::Params
+) -> edwards::Point<E, Subgroup>
+    where E::Params: Sync,
+          Subgroup: Send + Sync
+{
+    assert_eq!(bases.len(), scalars.len());
+
+    let c = window_size(bases.len());
+    let windows: Vec<usize> = (0..num_windows(scalars, c)).collect();
+
+    let cpus = num_cpus::get();
+    let chunk_size = cmp::max(1, (windows.len() + cpus - 1) / cpus);
+
+    let window_sums = crossbeam::scope(|scope| {
+        let handles: Vec<_> = windows.chunks(chunk_size).map(|chunk| {
+            scope.spawn(move || {
+                chunk.iter().map(|&i| window_sum(bases, scalars, i * c, c, params)).collect::<Vec<_>>()
+            })
+        }).collect();
+
+        handles.into_iter().flat_map(|h| h.join()).collect()
+    });
+
+    combine(window_sums, c, params)
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, XorShiftRng, Rand};
+    use pairing::Field;
+    use pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, Unknown, fs};
+    use super::*;
+
+    fn naive_multiexp(
+        bases: &[edwards::Point<Bls12, Unknown>],
+        scalars: &[fs::FsRepr],
+        params: &JubjubBls12
+    ) -> edwards::Point<Bls12, Unknown>
+    {
+        let mut res = edwards::Point::zero();
+
+        for (base, scalar) in bases.iter().zip(scalars.iter()) {
+            res = res.add(&base.mul(*scalar, params), params);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_multiexp() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &n in &[0, 1, 2, 5, 31, 32, 100] {
+            let bases: Vec<_> = (0..n).map(|_| edwards::Point::rand(rng, params)).collect();
+            let scalars: Vec<_> = (0..n).map(|_| fs::Fs::rand(rng).into_repr()).collect();
+
+            let expected = naive_multiexp(&bases, &scalars, params);
+
+            assert!(multiexp(&bases, &scalars, params) == expected);
+            assert!(multiexp_parallel(&bases, &scalars, params) == expected);
+        }
+    }
+
+    #[test]
+    fn test_multiexp_edge_cases() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut minus_one = fs::Fs::one();
+        minus_one.negate();
+
+        let scalars = vec![
+            fs::FsRepr::from(0),
+            fs::FsRepr::from(1),
+            fs::FsRepr::from(7),
+            fs::FsRepr::from(8),
+            fs::FsRepr([u64::max_value(); 4]),
+            minus_one.into_repr(),
+            fs::Fs::char()
+        ];
+        let bases: Vec<_> = scalars.iter().map(|_| edwards::Point::rand(rng, params)).collect();
+
+        let expected = naive_multiexp(&bases, &scalars, params);
+
+        assert!(multiexp(&bases, &scalars, params) == expected);
+        assert!(multiexp_parallel(&bases, &scalars, params) == expected);
+
+        // All scalars zero
+        let zeros = vec![fs::FsRepr::from(0); bases.len()];
+        assert!(multiexp(&bases, &zeros, params) == edwards::Point::zero());
+    }
+}
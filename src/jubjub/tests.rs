@@ -1,6 +1,7 @@
 use super::{
     JubjubEngine,
     JubjubParams,
+    FixedGenerators,
     PrimeOrder,
     montgomery,
     edwards
@@ -14,7 +15,9 @@ use pairing::{
     LegendreSymbol
 };
 
-use rand::{XorShiftRng, SeedableRng, Rand};
+use rand::{XorShiftRng, SeedableRng, Rand, Rng};
+
+use std::time::Instant;
 
 pub fn test_suite<E: JubjubEngine>(params: &E::Params) {
     test_back_and_forth::<E>(params);
@@ -27,6 +30,10 @@ pub fn test_suite<E: JubjubEngine>(params: &E::Params) {
     test_mul_associativity::<E>(params);
     test_loworder::<E>(params);
     test_read_write::<E>(params);
+    test_fixed_base_mul::<E>(params);
+    test_mul_wnaf::<E>(params);
+    test_affine::<E>(params);
+    test_mul_secret::<E>(params);
 }
 
 fn is_on_mont_curve<E: JubjubEngine, P: JubjubParams<E>>(
@@ -261,6 +268,214 @@ fn test_read_write<E: JubjubEngine>(params: &E::Params) {
     }
 }
 
+fn test_fixed_base_mul<E: JubjubEngine>(params: &E::Params) {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let bases = [
+        FixedGenerators::ProofGenerationKey,
+        FixedGenerators::NoteCommitmentRandomness,
+        FixedGenerators::NullifierPosition,
+        FixedGenerators::ValueCommitmentValue,
+        FixedGenerators::ValueCommitmentRandomness,
+        FixedGenerators::SpendingKeyGenerator
+    ];
+
+    let mut minus_one = E::Fs::one();
+    minus_one.negate();
+
+    let mut all_ones = <E::Fs as PrimeField>::Repr::default();
+    for limb in all_ones.as_mut() {
+        *limb = u64::max_value();
+    }
+
+    for &base in &bases {
+        let g = params.generator(base);
+
+        assert!(edwards::Point::fixed_base_mul(base, E::Fs::zero(), params) == edwards::Point::zero());
+        assert!(edwards::Point::fixed_base_mul(base, E::Fs::one(), params) == *g);
+        assert!(edwards::Point::fixed_base_mul(base, minus_one, params) == g.negate());
+        assert!(edwards::Point::fixed_base_mul(base, E::Fs::char(), params) == edwards::Point::zero());
+        assert!(edwards::Point::fixed_base_mul(base, all_ones, params) == g.mul(all_ones, params));
+        assert!(edwards::Point::fixed_base_mul(base, 1234567u64, params) == g.mul(1234567u64, params));
+
+        for _ in 0..50 {
+            let s = E::Fs::rand(rng);
+
+            assert!(edwards::Point::fixed_base_mul(base, s, params) == g.mul(s, params));
+        }
+    }
+}
+
+fn test_mul_wnaf<E: JubjubEngine>(params: &E::Params) {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut minus_one = E::Fs::one();
+    minus_one.negate();
+
+    for _ in 0..100 {
+        let p = edwards::Point::<E, _>::rand(rng, params);
+        let s = E::Fs::rand(rng);
+
+        assert!(p.mul_wnaf(s, params) == p.mul(s, params));
+        assert!(p.mul_wnaf(E::Fs::zero(), params) == edwards::Point::zero());
+        assert!(p.mul_wnaf(E::Fs::one(), params) == p);
+        assert!(p.mul_wnaf(minus_one, params) == p.mul(minus_one, params));
+        assert!(p.mul_wnaf(E::Fs::char(), params) == p.mul(E::Fs::char(), params));
+    }
+
+    for i in 0..1000u64 {
+        let p = edwards::Point::<E, _>::rand(rng, params);
+
+        assert!(p.mul_wnaf(i, params) == p.mul(i, params));
+    }
+}
+
+fn test_affine<E: JubjubEngine>(params: &E::Params) {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    assert!(edwards::Point::<E, PrimeOrder>::batch_normalize(&[]).is_empty());
+
+    let mut points = vec![edwards::Point::zero()];
+    for _ in 0..100 {
+        let p = edwards::Point::<E, _>::rand(rng, params);
+        let q = edwards::Point::<E, _>::rand(rng, params);
+
+        // Leave z != 1
+        points.push(p.add(&q, params));
+    }
+
+    let affine = edwards::Point::batch_normalize(&points);
+    assert_eq!(affine.len(), points.len());
+    assert!(affine[0] == edwards::AffinePoint::zero());
+
+    for (p, a) in points.iter().zip(affine.iter()) {
+        assert!(p.into_affine() == *a);
+        assert!(p.into_xy() == a.into_xy());
+        assert!(a.into_projective() == *p);
+        assert!(a.negate().into_projective() == p.negate());
+
+        let q = edwards::Point::<E, _>::rand(rng, params).double(params);
+
+        assert!(q.add_mixed(a, params) == q.add(p, params));
+        assert!(q.add_mixed(&a.negate(), params) == q.add(&p.negate(), params));
+        assert!(p.add_mixed(a, params) == p.double(params));
+    }
+}
+
+fn test_mul_secret<E: JubjubEngine>(params: &E::Params) {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut minus_one = E::Fs::one();
+    minus_one.negate();
+
+    for _ in 0..50 {
+        let p = edwards::Point::<E, _>::rand(rng, params);
+        let s = E::Fs::rand(rng);
+
+        assert!(p.mul_secret(s, params) == p.mul(s, params));
+        assert!(p.mul_secret(E::Fs::zero(), params) == edwards::Point::zero());
+        assert!(p.mul_secret(E::Fs::one(), params) == p);
+        assert!(p.mul_secret(minus_one, params) == p.mul(minus_one, params));
+        assert!(p.mul_secret(E::Fs::char(), params) == p.mul(E::Fs::char(), params));
+    }
+
+    let bases = [
+        FixedGenerators::ProofGenerationKey,
+        FixedGenerators::NoteCommitmentRandomness,
+        FixedGenerators::NullifierPosition,
+        FixedGenerators::ValueCommitmentValue,
+        FixedGenerators::ValueCommitmentRandomness,
+        FixedGenerators::SpendingKeyGenerator
+    ];
+
+    for &base in &bases {
+        assert!(edwards::Point::fixed_base_mul_secret(base, E::Fs::zero(), params) == edwards::Point::zero());
+        assert!(edwards::Point::fixed_base_mul_secret(base, minus_one, params) == params.generator(base).negate());
+
+        for _ in 0..10 {
+            let s = E::Fs::rand(rng);
+
+            assert!(edwards::Point::fixed_base_mul_secret(base, s, params) == edwards::Point::fixed_base_mul(base, s, params));
+        }
+    }
+}
+
+/// Times `f` on interleaved inputs from two classes and compares the
+/// running times with Welch's t-test, as dudect does, returning the
+/// t statistic. The slowest tenth of the measurements is discarded,
+/// as it is dominated by interruptions.
+fn dudect<T, R, F: FnMut(&T) -> R>(
+    class0: &[T],
+    class1: &[T],
+    mut f: F
+) -> f64
+{
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let mut order: Vec<(usize, usize)> = (0..class0.len()).map(|i| (0, i))
+                                         .chain((0..class1.len()).map(|i| (1, i)))
+                                         .collect();
+    rng.shuffle(&mut order);
+
+    let mut results = Vec::with_capacity(order.len());
+    let mut times = Vec::with_capacity(order.len());
+
+    for &(class, i) in &order {
+        let input = if class == 0 { &class0[i] } else { &class1[i] };
+
+        let start = Instant::now();
+        let res = f(input);
+        let elapsed = start.elapsed();
+
+        results.push(res);
+        times.push((class, elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64));
+    }
+
+    let mut sorted: Vec<f64> = times.iter().map(|&(_, t)| t).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let cutoff = sorted[sorted.len() * 9 / 10];
+
+    let stats = |class: usize| {
+        let samples: Vec<f64> = times.iter()
+                                     .filter(|&&(c, t)| c == class && t <= cutoff)
+                                     .map(|&(_, t)| t)
+                                     .collect();
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let var = samples.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / (n - 1.0);
+
+        (n, mean, var)
+    };
+
+    let (n0, mean0, var0) = stats(0);
+    let (n1, mean1, var1) = stats(1);
+
+    (mean0 - mean1) / (var0 / n0 + var1 / n1).sqrt()
+}
+
+/// Checks that the running time of the multiplications for secret
+/// scalars does not measurably depend on the scalar, comparing an
+/// all-zero scalar with random ones over `samples` runs of each. A t
+/// statistic above 10 in magnitude is dudect's threshold for a
+/// definite leak. This is sensitive to load on the machine, so it is
+/// not part of `test_suite`.
+pub fn test_secret_timing<E: JubjubEngine>(params: &E::Params, samples: usize) {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let zeros = vec![E::Fs::zero(); samples];
+    let randoms: Vec<E::Fs> = (0..samples).map(|_| E::Fs::rand(rng)).collect();
+
+    let p = edwards::Point::<E, _>::rand(rng, params);
+
+    let t = dudect(&zeros, &randoms, |s| p.mul_secret(*s, params));
+    assert!(t.abs() < 10.0, "mul_secret leaks timing: t = {}", t);
+
+    let t = dudect(&zeros, &randoms, |s| {
+        edwards::Point::fixed_base_mul_secret(FixedGenerators::SpendingKeyGenerator, *s, params)
+    });
+    assert!(t.abs() < 10.0, "fixed_base_mul_secret leaks timing: t = {}", t);
+}
+
 fn test_rand<E: JubjubEngine>(params: &E::Params) {
     let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
 
@@ -0,0 +1,920 @@
+//! Derivation of Sapling keys from a seed.
+//!
+//! A 32-byte spending key `sk` is expanded with PRF^expand into the
+//! spend authorizing key `ask`, the proof authorizing key `rsk` and the
+//! outgoing viewing key `ovk`. From these we obtain the
+//! `ProofGenerationKey { ak, rsk }` consumed by the `Spend` circuit,
+//! where `ak = [ask] G` over the spending key generator, and from it the
+//! `ViewingKey` used to derive payment addresses.
+//!
+//! Extended keys add a chain code so that a tree of keys can be derived
+//! from a single seed. Hardened children can only be derived from an
+//! extended spending key, while non-hardened children can also be derived
+//! from the corresponding extended full viewing key.
+
+use pairing::Field;
+
+use blake2_rfc::blake2b::Blake2b;
+use blake2_rfc::blake2s::Blake2s;
+
+use byteorder::{
+    LittleEndian,
+    ReadBytesExt,
+    WriteBytesExt
+};
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use constants;
+
+use jubjub::{
+    FixedGenerators,
+    JubjubEngine,
+    edwards
+};
+
+use primitives::{
+    Diversifier,
+    IncomingViewingKey,
+    PaymentAddress,
+    ProofGenerationKey,
+    ViewingKey
+};
+
+use util::{
+    hash_to_scalar,
+    read_scalar,
+    write_scalar,
+    zeroize,
+    zeroize_bytes
+};
+
+/// PRF^expand(sk, t) = BLAKE2b-512(sk | t), where `t` is given
+/// as a sequence of byte strings that are concatenated.
+fn prf_expand(sk: &[u8], ts: &[&[u8]]) -> [u8; 64] {
+    let mut h = Blake2b::with_params(64, &[], &[], constants::PRF_EXPAND_PERSONALIZATION);
+    h.update(sk);
+    for t in ts {
+        h.update(t);
+    }
+    let h = h.finalize();
+
+    let mut output = [0u8; 64];
+    output.copy_from_slice(h.as_ref());
+
+    output
+}
+
+/// ToScalar(PRF^expand(sk, [t]))
+fn prf_expand_to_scalar<E: JubjubEngine>(sk: &[u8], t: u8) -> E::Fs {
+    hash_to_scalar::<E>(constants::PRF_EXPAND_PERSONALIZATION, sk, &[t])
+}
+
+/// The outgoing viewing key, which allows the sender of a note to
+/// recover it later.
+#[derive(PartialEq)]
+pub struct OutgoingViewingKey(pub [u8; 32]);
+
+impl Drop for OutgoingViewingKey {
+    fn drop(&mut self) {
+        zeroize_bytes(&mut self.0);
+    }
+}
+
+impl OutgoingViewingKey {
+    /// Copies the key. This is not `Clone`, so that copies are
+    /// always explicit.
+    pub fn clone_secret(&self) -> Self {
+        OutgoingViewingKey(self.0)
+    }
+
+    fn derive_child(&self, i_l: &[u8]) -> Self {
+        let mut ovk = [0u8; 32];
+        ovk.copy_from_slice(&prf_expand(i_l, &[&[0x15], &self.0])[..32]);
+
+        OutgoingViewingKey(ovk)
+    }
+}
+
+/// The keys expanded from a spending key.
+pub struct ExpandedSpendingKey<E: JubjubEngine> {
+    /// The spend authorizing key
+    pub ask: E::Fs,
+    /// The proof authorizing key
+    pub rsk: E::Fs,
+    /// The outgoing viewing key
+    pub ovk: OutgoingViewingKey
+}
+
+impl<E: JubjubEngine> Drop for ExpandedSpendingKey<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.ask, E::Fs::zero());
+        zeroize(&mut self.rsk, E::Fs::zero());
+    }
+}
+
+impl<E: JubjubEngine> ExpandedSpendingKey<E> {
+    /// Copies the keys. This is not `Clone`, so that copies of
+    /// `ask` and `rsk` are always explicit.
+    pub fn clone_secret(&self) -> Self {
+        ExpandedSpendingKey {
+            ask: self.ask,
+            rsk: self.rsk,
+            ovk: self.ovk.clone_secret()
+        }
+    }
+
+    pub fn from_spending_key(sk: &[u8]) -> Self {
+        let ask = prf_expand_to_scalar::<E>(sk, 0x00);
+        let rsk = prf_expand_to_scalar::<E>(sk, 0x01);
+        let mut ovk = [0u8; 32];
+        ovk.copy_from_slice(&prf_expand(sk, &[&[0x02]])[..32]);
+
+        ExpandedSpendingKey {
+            ask: ask,
+            rsk: rsk,
+            ovk: OutgoingViewingKey(ovk)
+        }
+    }
+
+    /// The key given to the prover in order to create `Spend` proofs.
+    pub fn proof_generation_key(&self, params: &E::Params) -> ProofGenerationKey<E> {
+        ProofGenerationKey {
+            ak: edwards::Point::fixed_base_mul_secret(FixedGenerators::SpendingKeyGenerator, self.ask, params),
+            rsk: self.rsk
+        }
+    }
+
+    fn derive_child(&self, i_l: &[u8]) -> Self {
+        let mut ask = prf_expand_to_scalar::<E>(i_l, 0x13);
+        ask.add_assign(&self.ask);
+        let mut rsk = prf_expand_to_scalar::<E>(i_l, 0x14);
+        rsk.add_assign(&self.rsk);
+
+        ExpandedSpendingKey {
+            ask: ask,
+            rsk: rsk,
+            ovk: self.ovk.derive_child(i_l)
+        }
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let ask = read_scalar::<E::Fs, _>(&mut reader)?;
+        let rsk = read_scalar::<E::Fs, _>(&mut reader)?;
+        let mut ovk = [0u8; 32];
+        reader.read_exact(&mut ovk)?;
+
+        Ok(ExpandedSpendingKey {
+            ask: ask,
+            rsk: rsk,
+            ovk: OutgoingViewingKey(ovk)
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_scalar::<E::Fs, _>(&self.ask, &mut writer)?;
+        write_scalar::<E::Fs, _>(&self.rsk, &mut writer)?;
+        writer.write_all(&self.ovk.0)
+    }
+}
+
+/// The fingerprint of a full viewing key, which identifies it.
+#[derive(Copy, Clone, PartialEq)]
+pub struct FvkFingerprint(pub [u8; 32]);
+
+/// The first four bytes of a full viewing key fingerprint, which
+/// identify the parent of an extended key.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FvkTag(pub [u8; 4]);
+
+impl FvkFingerprint {
+    pub fn tag(&self) -> FvkTag {
+        let mut tag = [0u8; 4];
+        tag.copy_from_slice(&self.0[..4]);
+
+        FvkTag(tag)
+    }
+}
+
+/// A viewing key together with the outgoing viewing key, which
+/// can view all incoming and outgoing notes of a spending key.
+pub struct FullViewingKey<E: JubjubEngine> {
+    pub vk: ViewingKey<E>,
+    pub ovk: OutgoingViewingKey
+}
+
+impl<E: JubjubEngine> Clone for FullViewingKey<E> {
+    fn clone(&self) -> Self {
+        FullViewingKey {
+            vk: self.vk.clone(),
+            ovk: self.ovk.clone_secret()
+        }
+    }
+}
+
+impl<E: JubjubEngine> FullViewingKey<E> {
+    pub fn from_expanded_spending_key(
+        expsk: &ExpandedSpendingKey<E>,
+        params: &E::Params
+    ) -> Self
+    {
+        FullViewingKey {
+            vk: expsk.proof_generation_key(params).into_viewing_key(params),
+            ovk: expsk.ovk.clone_secret()
+        }
+    }
+
+    /// Computes BLAKE2b-256(ak | rk | ovk).
+    pub fn fingerprint(&self) -> FvkFingerprint {
+        let mut fvk = vec![];
+        self.write(&mut fvk).expect("should be able to serialize a full viewing key");
+
+        let mut h = Blake2b::with_params(32, &[], &[], constants::SAPLING_FVK_FINGERPRINT_PERSONALIZATION);
+        h.update(&fvk);
+        let h = h.finalize();
+
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(h.as_ref());
+
+        FvkFingerprint(fingerprint)
+    }
+
+    fn derive_child(&self, i_l: &[u8], params: &E::Params) -> Self {
+        let i_ask = prf_expand_to_scalar::<E>(i_l, 0x13);
+        let i_rsk = prf_expand_to_scalar::<E>(i_l, 0x14);
+
+        FullViewingKey {
+            vk: ViewingKey {
+                ak: edwards::Point::fixed_base_mul_secret(FixedGenerators::SpendingKeyGenerator, i_ask, params)
+                          .add(&self.vk.ak, params),
+                rk: edwards::Point::fixed_base_mul_secret(FixedGenerators::ProofGenerationKey, i_rsk, params)
+                          .add(&self.vk.rk, params)
+            },
+            ovk: self.ovk.derive_child(i_l)
+        }
+    }
+
+    pub fn diversifier_key(&self) -> DiversifierKey {
+        DiversifierKey::from_viewing_key(&self.vk)
+    }
+
+    /// The address with the first valid diversifier, or `None` in the
+    /// negligibly likely case that no diversifier is valid.
+    pub fn default_address(
+        &self,
+        params: &E::Params
+    ) -> Option<(DiversifierIndex, PaymentAddress<E>)>
+    {
+        self.diversifier_key()
+            .addresses(self.vk.ivk(), DiversifierIndex::new(), params)
+            .next()
+    }
+
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> io::Result<Self> {
+        let vk = ViewingKey::read(&mut reader, params)?;
+        let mut ovk = [0u8; 32];
+        reader.read_exact(&mut ovk)?;
+
+        Ok(FullViewingKey {
+            vk: vk,
+            ovk: OutgoingViewingKey(ovk)
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.vk.write(&mut writer)?;
+        writer.write_all(&self.ovk.0)
+    }
+}
+
+/// The index of a diversifier, as an 88-bit little endian integer.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DiversifierIndex(pub [u8; 11]);
+
+impl DiversifierIndex {
+    pub fn new() -> Self {
+        DiversifierIndex([0; 11])
+    }
+
+    /// Increments the index, returning an error if
+    /// the index space is exhausted.
+    pub fn increment(&mut self) -> Result<(), ()> {
+        for b in self.0.iter_mut() {
+            *b = b.wrapping_add(1);
+            if *b != 0 {
+                return Ok(());
+            }
+        }
+
+        Err(())
+    }
+}
+
+impl From<u64> for DiversifierIndex {
+    fn from(i: u64) -> Self {
+        let mut index = [0u8; 11];
+        (&mut index[..8]).write_u64::<LittleEndian>(i).unwrap();
+
+        DiversifierIndex(index)
+    }
+}
+
+/// The key used to derive the diversifiers of a viewing key. Without
+/// it, diversified addresses of the same key cannot be linked.
+#[derive(Copy, Clone, PartialEq)]
+pub struct DiversifierKey(pub [u8; 32]);
+
+impl DiversifierKey {
+    /// Computes BLAKE2s(ak | rk).
+    pub fn from_viewing_key<E: JubjubEngine>(vk: &ViewingKey<E>) -> Self {
+        let mut preimage = [0u8; 64];
+        vk.ak.write(&mut preimage[0..32]).unwrap();
+        vk.rk.write(&mut preimage[32..64]).unwrap();
+
+        let mut h = Blake2s::with_params(32, &[], &[], constants::DIVERSIFIER_KEY_PERSONALIZATION);
+        h.update(&preimage);
+        let h = h.finalize();
+
+        let mut dk = [0u8; 32];
+        dk.copy_from_slice(h.as_ref());
+
+        DiversifierKey(dk)
+    }
+
+    /// Derives the diversifier at index `j`, which may be invalid.
+    pub fn diversifier(&self, j: DiversifierIndex) -> Diversifier {
+        let mut h = Blake2s::with_params(11, &self.0, &[], constants::DIVERSIFIER_PERSONALIZATION);
+        h.update(&j.0);
+        let h = h.finalize();
+
+        let mut d = [0u8; 11];
+        d.copy_from_slice(h.as_ref());
+
+        Diversifier(d)
+    }
+
+    /// Finds the first valid diversifier at or after index `j`,
+    /// returning it with its index.
+    pub fn find_diversifier<E: JubjubEngine>(
+        &self,
+        mut j: DiversifierIndex,
+        params: &E::Params
+    ) -> Option<(DiversifierIndex, Diversifier)>
+    {
+        loop {
+            let d = self.diversifier(j);
+            if d.g_d::<E>(params).is_ok() {
+                return Some((j, d));
+            }
+
+            if j.increment().is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// Iterates over the addresses of `ivk` with valid diversifiers,
+    /// starting at index `start`.
+    pub fn addresses<'a, E: JubjubEngine>(
+        &self,
+        ivk: IncomingViewingKey<E>,
+        start: DiversifierIndex,
+        params: &'a E::Params
+    ) -> DiversifiedAddresses<'a, E>
+    {
+        DiversifiedAddresses {
+            dk: *self,
+            ivk: ivk,
+            next: Some(start),
+            params: params
+        }
+    }
+}
+
+/// An iterator over the diversified addresses of an incoming
+/// viewing key, yielding each with its diversifier index.
+pub struct DiversifiedAddresses<'a, E: JubjubEngine> {
+    dk: DiversifierKey,
+    ivk: IncomingViewingKey<E>,
+    next: Option<DiversifierIndex>,
+    params: &'a E::Params
+}
+
+impl<'a, E: JubjubEngine> Iterator for DiversifiedAddresses<'a, E> {
+    type Item = (DiversifierIndex, PaymentAddress<E>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = match self.next {
+            Some(start) => start,
+            None => return None
+        };
+
+        match self.dk.find_diversifier::<E>(start, self.params) {
+            Some((j, d)) => {
+                let mut next = j;
+                self.next = match next.increment() {
+                    Ok(()) => Some(next),
+                    Err(()) => None
+                };
+
+                let to = self.ivk.into_payment_address(d, self.params)
+                                 .expect("diversifier should be valid");
+
+                Some((j, to))
+            },
+            None => {
+                self.next = None;
+                None
+            }
+        }
+    }
+}
+
+/// The index of a child key. Hardened indices must be less than 2^31.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ChildIndex {
+    NonHardened(u32),
+    Hardened(u32)
+}
+
+impl ChildIndex {
+    fn from_index(i: u32) -> Self {
+        if i >= (1 << 31) {
+            ChildIndex::Hardened(i - (1 << 31))
+        } else {
+            ChildIndex::NonHardened(i)
+        }
+    }
+
+    /// Returns `None` for indices that are not less than 2^31.
+    fn to_index(&self) -> Option<u32> {
+        match *self {
+            ChildIndex::Hardened(i) => i.checked_add(1 << 31),
+            ChildIndex::NonHardened(i) if i < (1 << 31) => Some(i),
+            ChildIndex::NonHardened(_) => None
+        }
+    }
+
+    fn to_le_bytes(&self) -> Option<[u8; 4]> {
+        self.to_index().map(|i| {
+            let mut bytes = [0u8; 4];
+            (&mut bytes[..]).write_u32::<LittleEndian>(i).unwrap();
+
+            bytes
+        })
+    }
+}
+
+/// The length of the encoding of an `ExtendedSpendingKey` or an
+/// `ExtendedFullViewingKey`: the depth, the parent's tag, the child
+/// index and the chain code, followed by 96 bytes of key material.
+pub const EXTENDED_KEY_SIZE: usize = 1 + 4 + 4 + 32 + 96;
+
+/// The chain code of an extended key.
+#[derive(Copy, Clone, PartialEq)]
+pub struct ChainCode(pub [u8; 32]);
+
+fn read_metadata<R: Read>(mut reader: R) -> io::Result<(u8, FvkTag, ChildIndex, ChainCode)> {
+    let depth = reader.read_u8()?;
+    let mut tag = [0u8; 4];
+    reader.read_exact(&mut tag)?;
+    let i = reader.read_u32::<LittleEndian>()?;
+    let mut c = [0u8; 32];
+    reader.read_exact(&mut c)?;
+
+    Ok((depth, FvkTag(tag), ChildIndex::from_index(i), ChainCode(c)))
+}
+
+fn write_metadata<W: Write>(
+    mut writer: W,
+    depth: u8,
+    parent_fvk_tag: &FvkTag,
+    child_index: &ChildIndex,
+    chain_code: &ChainCode
+) -> io::Result<()>
+{
+    let i = child_index.to_index().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "child index is not less than 2^31")
+    })?;
+
+    writer.write_u8(depth)?;
+    writer.write_all(&parent_fvk_tag.0)?;
+    writer.write_u32::<LittleEndian>(i)?;
+    writer.write_all(&chain_code.0)
+}
+
+/// A spending key at some position in a tree of keys.
+pub struct ExtendedSpendingKey<E: JubjubEngine> {
+    pub depth: u8,
+    pub parent_fvk_tag: FvkTag,
+    pub child_index: ChildIndex,
+    pub chain_code: ChainCode,
+    pub expsk: ExpandedSpendingKey<E>
+}
+
+impl<E: JubjubEngine> Drop for ExtendedSpendingKey<E> {
+    fn drop(&mut self) {
+        zeroize_bytes(&mut self.chain_code.0);
+    }
+}
+
+impl<E: JubjubEngine> ExtendedSpendingKey<E> {
+    /// Copies the key, as with `ExpandedSpendingKey::clone_secret`.
+    pub fn clone_secret(&self) -> Self {
+        ExtendedSpendingKey {
+            depth: self.depth,
+            parent_fvk_tag: self.parent_fvk_tag,
+            child_index: self.child_index,
+            chain_code: self.chain_code,
+            expsk: self.expsk.clone_secret()
+        }
+    }
+
+    /// Derives the root of the tree of keys from a seed.
+    pub fn master(seed: &[u8]) -> Self {
+        let mut h = Blake2b::with_params(64, &[], &[], constants::SAPLING_MASTER_KEY_PERSONALIZATION);
+        h.update(seed);
+        let i = h.finalize();
+
+        let sk_m = &i.as_ref()[..32];
+        let mut c_m = [0u8; 32];
+        c_m.copy_from_slice(&i.as_ref()[32..]);
+
+        ExtendedSpendingKey {
+            depth: 0,
+            parent_fvk_tag: FvkTag([0u8; 4]),
+            child_index: ChildIndex::NonHardened(0),
+            chain_code: ChainCode(c_m),
+            expsk: ExpandedSpendingKey::from_spending_key(sk_m)
+        }
+    }
+
+    /// Derives the key at the end of a path from this key. Returns
+    /// an error if any step of the derivation fails.
+    pub fn from_path(&self, path: &[ChildIndex], params: &E::Params) -> Result<Self, ()> {
+        let mut xsk = self.clone_secret();
+        for &i in path {
+            xsk = xsk.derive_child(i, params)?;
+        }
+
+        Ok(xsk)
+    }
+
+    /// Derives a child key. Returns an error if the index is not
+    /// less than 2^31, or if this key is at the maximum depth.
+    pub fn derive_child(&self, i: ChildIndex, params: &E::Params) -> Result<Self, ()> {
+        let i_bytes = i.to_le_bytes().ok_or(())?;
+        let depth = self.depth.checked_add(1).ok_or(())?;
+
+        let fvk = FullViewingKey::from_expanded_spending_key(&self.expsk, params);
+
+        let tmp = match i {
+            ChildIndex::Hardened(_) => {
+                let mut expsk = vec![];
+                self.expsk.write(&mut expsk).expect("should be able to serialize an expanded spending key");
+
+                prf_expand(&self.chain_code.0, &[&[0x11], &expsk, &i_bytes])
+            },
+            ChildIndex::NonHardened(_) => {
+                let mut fvk_bytes = vec![];
+                fvk.write(&mut fvk_bytes).expect("should be able to serialize a full viewing key");
+
+                prf_expand(&self.chain_code.0, &[&[0x12], &fvk_bytes, &i_bytes])
+            }
+        };
+        let i_l = &tmp[..32];
+        let mut c_i = [0u8; 32];
+        c_i.copy_from_slice(&tmp[32..]);
+
+        Ok(ExtendedSpendingKey {
+            depth: depth,
+            parent_fvk_tag: fvk.fingerprint().tag(),
+            child_index: i,
+            chain_code: ChainCode(c_i),
+            expsk: self.expsk.derive_child(i_l)
+        })
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let (depth, parent_fvk_tag, child_index, chain_code) = read_metadata(&mut reader)?;
+        let expsk = ExpandedSpendingKey::read(&mut reader)?;
+
+        Ok(ExtendedSpendingKey {
+            depth: depth,
+            parent_fvk_tag: parent_fvk_tag,
+            child_index: child_index,
+            chain_code: chain_code,
+            expsk: expsk
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_metadata(&mut writer, self.depth, &self.parent_fvk_tag, &self.child_index, &self.chain_code)?;
+        self.expsk.write(&mut writer)
+    }
+}
+
+/// A full viewing key at some position in a tree of keys.
+#[derive(Clone)]
+pub struct ExtendedFullViewingKey<E: JubjubEngine> {
+    pub depth: u8,
+    pub parent_fvk_tag: FvkTag,
+    pub child_index: ChildIndex,
+    pub chain_code: ChainCode,
+    pub fvk: FullViewingKey<E>
+}
+
+impl<E: JubjubEngine> ExtendedFullViewingKey<E> {
+    pub fn from_extended_spending_key(
+        xsk: &ExtendedSpendingKey<E>,
+        params: &E::Params
+    ) -> Self
+    {
+        ExtendedFullViewingKey {
+            depth: xsk.depth,
+            parent_fvk_tag: xsk.parent_fvk_tag,
+            child_index: xsk.child_index,
+            chain_code: xsk.chain_code,
+            fvk: FullViewingKey::from_expanded_spending_key(&xsk.expsk, params)
+        }
+    }
+
+    /// Derives a non-hardened child key. Returns an error for
+    /// hardened indices, which require the spending key, or if this
+    /// key is at the maximum depth.
+    pub fn derive_child(&self, i: ChildIndex, params: &E::Params) -> Result<Self, ()> {
+        let depth = self.depth.checked_add(1).ok_or(())?;
+
+        let tmp = match i {
+            ChildIndex::Hardened(_) => return Err(()),
+            ChildIndex::NonHardened(_) => {
+                let mut fvk = vec![];
+                self.fvk.write(&mut fvk).expect("should be able to serialize a full viewing key");
+
+                prf_expand(&self.chain_code.0, &[&[0x12], &fvk, &i.to_le_bytes().ok_or(())?])
+            }
+        };
+        let i_l = &tmp[..32];
+        let mut c_i = [0u8; 32];
+        c_i.copy_from_slice(&tmp[32..]);
+
+        Ok(ExtendedFullViewingKey {
+            depth: depth,
+            parent_fvk_tag: self.fvk.fingerprint().tag(),
+            child_index: i,
+            chain_code: ChainCode(c_i),
+            fvk: self.fvk.derive_child(i_l, params)
+        })
+    }
+
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> io::Result<Self> {
+        let (depth, parent_fvk_tag, child_index, chain_code) = read_metadata(&mut reader)?;
+        let fvk = FullViewingKey::read(&mut reader, params)?;
+
+        Ok(ExtendedFullViewingKey {
+            depth: depth,
+            parent_fvk_tag: parent_fvk_tag,
+            child_index: child_index,
+            chain_code: chain_code,
+            fvk: fvk
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_metadata(&mut writer, self.depth, &self.parent_fvk_tag, &self.child_index, &self.chain_code)?;
+        self.fvk.write(&mut writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, JubjubParams};
+    use primitives::Diversifier;
+    use super::*;
+
+    fn assert_fvk_eq(a: &FullViewingKey<Bls12>, b: &FullViewingKey<Bls12>) {
+        assert!(a.vk.ak == b.vk.ak);
+        assert!(a.vk.rk == b.vk.rk);
+        assert!(a.ovk == b.ovk);
+    }
+
+    #[test]
+    fn test_expanded_spending_key() {
+        let params = &JubjubBls12::new();
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[7u8; 32]);
+        let pgk = expsk.proof_generation_key(params);
+
+        assert!(pgk.ak == params.generator(FixedGenerators::SpendingKeyGenerator).mul(expsk.ask, params));
+        assert_eq!(pgk.rsk, expsk.rsk);
+
+        // The viewing key agrees with the proof generation key.
+        let fvk = FullViewingKey::from_expanded_spending_key(&expsk, params);
+        let vk = pgk.into_viewing_key(params);
+        assert!(fvk.vk.ak == vk.ak);
+        assert!(fvk.vk.rk == vk.rk);
+
+        // Different spending keys expand differently.
+        let other = ExpandedSpendingKey::<Bls12>::from_spending_key(&[8u8; 32]);
+        assert!(other.ask != expsk.ask);
+        assert!(other.rsk != expsk.rsk);
+        assert!(other.ovk != expsk.ovk);
+
+        let mut bytes = vec![];
+        expsk.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 96);
+        let expsk_2 = ExpandedSpendingKey::<Bls12>::read(&bytes[..]).unwrap();
+        assert_eq!(expsk_2.ask, expsk.ask);
+        assert_eq!(expsk_2.rsk, expsk.rsk);
+        assert!(expsk_2.ovk == expsk.ovk);
+    }
+
+    #[test]
+    fn test_derivation() {
+        let params = &JubjubBls12::new();
+
+        let m = ExtendedSpendingKey::<Bls12>::master(&[0u8; 32]);
+        let m_fvk = ExtendedFullViewingKey::from_extended_spending_key(&m, params);
+        assert_eq!(m.depth, 0);
+
+        // Non-hardened children agree between spending and viewing keys.
+        let i = ChildIndex::NonHardened(5);
+        let c = m.derive_child(i, params).unwrap();
+        let c_fvk = m_fvk.derive_child(i, params).unwrap();
+        assert_fvk_eq(
+            &FullViewingKey::from_expanded_spending_key(&c.expsk, params),
+            &c_fvk.fvk
+        );
+        assert!(c.chain_code == c_fvk.chain_code);
+        assert_eq!(c.depth, 1);
+        assert_eq!(c_fvk.depth, 1);
+        assert_eq!(c.child_index, i);
+        assert_eq!(c.parent_fvk_tag, m_fvk.fvk.fingerprint().tag());
+        assert_eq!(c_fvk.parent_fvk_tag, c.parent_fvk_tag);
+
+        // Hardened children cannot be derived from viewing keys.
+        let h = ChildIndex::Hardened(5);
+        assert!(m_fvk.derive_child(h, params).is_err());
+
+        let c_h = m.derive_child(h, params).unwrap();
+        assert_eq!(c_h.parent_fvk_tag, c.parent_fvk_tag);
+        assert!(c_h.chain_code != c.chain_code);
+        assert!(c_h.expsk.ask != c.expsk.ask);
+
+        // Paths compose.
+        let path = [ChildIndex::Hardened(32), ChildIndex::Hardened(133), ChildIndex::NonHardened(2)];
+        let p = m.from_path(&path, params).unwrap();
+        let q = m.derive_child(path[0], params).unwrap()
+                 .derive_child(path[1], params).unwrap()
+                 .derive_child(path[2], params).unwrap();
+        assert_eq!(p.depth, 3);
+        assert_eq!(p.expsk.ask, q.expsk.ask);
+        assert_eq!(p.expsk.rsk, q.expsk.rsk);
+
+        // Derived keys produce usable payment addresses.
+        let vk = p.expsk.proof_generation_key(params).into_viewing_key(params);
+        let mut found = false;
+        for i in 0..10u8 {
+            if vk.into_payment_address(Diversifier([i; 11]), params).is_ok() {
+                found = true;
+                break;
+            }
+        }
+        assert!(found);
+    }
+
+    #[test]
+    fn test_invalid_derivation() {
+        let params = &JubjubBls12::new();
+
+        let mut m = ExtendedSpendingKey::<Bls12>::master(&[0u8; 32]);
+        let mut m_fvk = ExtendedFullViewingKey::from_extended_spending_key(&m, params);
+
+        // Indices must be less than 2^31.
+        assert!(m.derive_child(ChildIndex::Hardened(1 << 31), params).is_err());
+        assert!(m.derive_child(ChildIndex::Hardened((1 << 31) - 1), params).is_ok());
+        assert!(m.derive_child(ChildIndex::NonHardened(1 << 31), params).is_err());
+        assert!(m_fvk.derive_child(ChildIndex::NonHardened(1 << 31), params).is_err());
+        assert!(m_fvk.derive_child(ChildIndex::NonHardened((1 << 31) - 1), params).is_ok());
+
+        // Out of range indices cannot be written either.
+        let mut bad = m.clone_secret();
+        bad.child_index = ChildIndex::Hardened(1 << 31);
+        assert!(bad.write(&mut vec![]).is_err());
+
+        // The depth cannot exceed 255.
+        m.depth = 255;
+        m_fvk.depth = 255;
+        assert!(m.derive_child(ChildIndex::Hardened(0), params).is_err());
+        assert!(m_fvk.derive_child(ChildIndex::NonHardened(0), params).is_err());
+
+        m.depth = 254;
+        m_fvk.depth = 254;
+        assert_eq!(m.derive_child(ChildIndex::Hardened(0), params).unwrap().depth, 255);
+        assert_eq!(m_fvk.derive_child(ChildIndex::NonHardened(0), params).unwrap().depth, 255);
+    }
+
+    #[test]
+    fn test_read_write() {
+        let params = &JubjubBls12::new();
+
+        let m = ExtendedSpendingKey::<Bls12>::master(b"a seed that is at least 32 bytes long");
+        let xsk = m.from_path(&[ChildIndex::Hardened(1), ChildIndex::NonHardened(1 << 30)], params).unwrap();
+        let xfvk = ExtendedFullViewingKey::from_extended_spending_key(&xsk, params);
+
+        let mut xsk_bytes = vec![];
+        xsk.write(&mut xsk_bytes).unwrap();
+        assert_eq!(xsk_bytes.len(), EXTENDED_KEY_SIZE);
+
+        let xsk_2 = ExtendedSpendingKey::<Bls12>::read(&xsk_bytes[..]).unwrap();
+        assert_eq!(xsk_2.depth, xsk.depth);
+        assert_eq!(xsk_2.parent_fvk_tag, xsk.parent_fvk_tag);
+        assert_eq!(xsk_2.child_index, xsk.child_index);
+        assert!(xsk_2.chain_code == xsk.chain_code);
+        assert_eq!(xsk_2.expsk.ask, xsk.expsk.ask);
+
+        let mut xfvk_bytes = vec![];
+        xfvk.write(&mut xfvk_bytes).unwrap();
+        assert_eq!(xfvk_bytes.len(), EXTENDED_KEY_SIZE);
+
+        let xfvk_2 = ExtendedFullViewingKey::<Bls12>::read(&xfvk_bytes[..], params).unwrap();
+        assert_eq!(xfvk_2.child_index, ChildIndex::NonHardened(1 << 30));
+        assert_fvk_eq(&xfvk_2.fvk, &xfvk.fvk);
+        assert!(xfvk_2.fvk.fingerprint() == xfvk.fvk.fingerprint());
+    }
+
+    #[test]
+    fn test_diversified_addresses() {
+        let params = &JubjubBls12::new();
+
+        let xsk = ExtendedSpendingKey::<Bls12>::master(&[1u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&xsk.expsk, params);
+        let dk = fvk.diversifier_key();
+        let ivk = fvk.vk.ivk();
+
+        let addresses: Vec<_> = dk.addresses(ivk.clone_secret(), DiversifierIndex::new(), params)
+                                  .take(10)
+                                  .collect();
+
+        let (j0, ref a0) = addresses[0];
+        let (j_default, default) = fvk.default_address(params).unwrap();
+        assert_eq!(j0, j_default);
+        assert!(a0.pk_d == default.pk_d);
+
+        for w in addresses.windows(2) {
+            let (j, ref a) = w[0];
+            let (k, ref b) = w[1];
+
+            // Indices strictly increase and skip only invalid diversifiers.
+            let mut next = j;
+            next.increment().unwrap();
+            while next != k {
+                assert!(dk.diversifier(next).g_d::<Bls12>(params).is_err());
+                next.increment().unwrap();
+            }
+
+            // Each address is the viewing key's address for its diversifier.
+            let expected = fvk.vk.into_payment_address(dk.diversifier(k), params).unwrap();
+            assert!(b.pk_d == expected.pk_d);
+            assert!(a.pk_d != b.pk_d);
+        }
+
+        // Iteration resumes deterministically from any index.
+        let (j5, ref a5) = addresses[5];
+        let (k, b) = dk.addresses(ivk, j5, params).next().unwrap();
+        assert_eq!(k, j5);
+        assert!(b.pk_d == a5.pk_d);
+
+        // Other keys derive unrelated diversifiers.
+        let other = xsk.derive_child(ChildIndex::Hardened(0), params).unwrap();
+        let other_dk = FullViewingKey::from_expanded_spending_key(&other.expsk, params).diversifier_key();
+        assert!(other_dk != dk);
+        assert!(other_dk.diversifier(j0).0 != dk.diversifier(j0).0);
+    }
+
+    #[test]
+    fn test_diversifier_index_increment() {
+        let mut j = DiversifierIndex::from(0xff);
+        j.increment().unwrap();
+        assert_eq!(j, DiversifierIndex::from(0x100));
+
+        let mut j = DiversifierIndex([0xff; 11]);
+        assert!(j.increment().is_err());
+
+        let params = &JubjubBls12::new();
+        let dk = DiversifierKey([0; 32]);
+        let mut addresses = dk.addresses(
+            IncomingViewingKey::<Bls12>(Field::one()),
+            DiversifierIndex([0xff; 11]),
+            params
+        );
+        // At most one address remains at the end of the index space.
+        addresses.next();
+        assert!(addresses.next().is_none());
+    }
+}
@@ -4,14 +4,41 @@ extern crate blake2_rfc;
 extern crate digest;
 extern crate rand;
 extern crate byteorder;
+extern crate crossbeam;
+extern crate num_cpus;
+
+#[cfg(feature = "serde")]
+extern crate serde;
 
 #[cfg(test)]
 #[macro_use]
 extern crate hex_literal;
 
+#[cfg(test)]
+extern crate sha2;
+
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
 pub mod jubjub;
 pub mod group_hash;
 pub mod circuit;
 pub mod pedersen_hash;
+pub mod poseidon;
 pub mod primitives;
 pub mod constants;
+pub mod error;
+pub mod merkle_tree;
+pub mod redjubjub;
+pub mod binding;
+pub mod note_encryption;
+pub mod keys;
+pub mod bech32;
+pub mod prover;
+pub mod verifier;
+pub mod public_inputs;
+
+mod util;
+
+#[cfg(feature = "serde")]
+mod serde_impls;
@@ -0,0 +1,813 @@
+//! An incremental, append-only Merkle tree of note commitments,
+//! hashed with the same Pedersen hash the `Spend` circuit uses to
+//! authenticate a note commitment against an anchor.
+
+use pairing::{
+    PrimeField,
+    PrimeFieldRepr,
+    BitIterator
+};
+
+use byteorder::{
+    LittleEndian,
+    ReadBytesExt,
+    WriteBytesExt
+};
+
+use std::collections::VecDeque;
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use jubjub::{
+    edwards,
+    JubjubEngine
+};
+
+use pedersen_hash::{
+    pedersen_hash_precomputed,
+    Personalization
+};
+
+use primitives::Note;
+
+/// The depth of the note commitment tree.
+pub const SAPLING_COMMITMENT_TREE_DEPTH: usize = 32;
+
+/// Computes the parent of two nodes at the given depth of the tree,
+/// where depth 0 combines two leaves. This mirrors the hash performed
+/// at each level of the authentication path in the `Spend` circuit.
+pub fn merkle_hash<E: JubjubEngine>(
+    depth: usize,
+    lhs: &E::Fr,
+    rhs: &E::Fr,
+    params: &E::Params
+) -> E::Fr
+{
+    pedersen_hash_precomputed::<E, _>(
+        Personalization::MerkleTree(depth),
+        merkle_hash_bits::<E>(lhs, rhs),
+        params
+    ).into_xy().0 // Injective encoding
+}
+
+/// Hashes each pair of adjacent nodes at the given depth as
+/// `merkle_hash` does, converting all of the hashes to affine
+/// coordinates with a single inversion. This is intended for
+/// rebuilding a layer of a tree at a time.
+pub fn merkle_hash_layer<E: JubjubEngine>(
+    depth: usize,
+    nodes: &[E::Fr],
+    params: &E::Params
+) -> Vec<E::Fr>
+{
+    assert!(nodes.len() % 2 == 0);
+
+    let hashes: Vec<_> = nodes.chunks(2).map(|pair| {
+        pedersen_hash_precomputed::<E, _>(
+            Personalization::MerkleTree(depth),
+            merkle_hash_bits::<E>(&pair[0], &pair[1]),
+            params
+        )
+    }).collect();
+
+    edwards::Point::batch_normalize(&hashes)
+                   .iter()
+                   .map(|p| p.into_xy().0) // Injective encoding
+                   .collect()
+}
+
+/// The bits of the two children hashed into their parent.
+fn merkle_hash_bits<E: JubjubEngine>(
+    lhs: &E::Fr,
+    rhs: &E::Fr
+) -> Vec<bool>
+{
+    let mut lhs: Vec<bool> = BitIterator::new(lhs.into_repr()).collect();
+    let mut rhs: Vec<bool> = BitIterator::new(rhs.into_repr()).collect();
+
+    // Little endian bit order
+    lhs.reverse();
+    rhs.reverse();
+
+    lhs.into_iter()
+       .take(E::Fr::NUM_BITS as usize)
+       .chain(rhs.into_iter().take(E::Fr::NUM_BITS as usize))
+       .collect()
+}
+
+/// Returns the roots of empty subtrees of each depth, from the empty
+/// leaf (depth 0) up to the root of an empty tree.
+pub fn empty_roots<E: JubjubEngine>(params: &E::Params) -> Vec<E::Fr> {
+    let mut roots = Vec::with_capacity(SAPLING_COMMITMENT_TREE_DEPTH + 1);
+    roots.push(Note::<E>::uncommitted());
+
+    for d in 0..SAPLING_COMMITMENT_TREE_DEPTH {
+        let next = merkle_hash::<E>(d, &roots[d], &roots[d], params);
+        roots.push(next);
+    }
+
+    roots
+}
+
+fn write_node<E: JubjubEngine, W: Write>(node: &E::Fr, mut writer: W) -> io::Result<()> {
+    let mut bytes = [0u8; 32];
+    node.into_repr().write_be(&mut bytes[..])?;
+
+    // Nodes are encoded in little endian byte order.
+    bytes.reverse();
+
+    writer.write_all(&bytes)
+}
+
+fn read_node<E: JubjubEngine, R: Read>(mut reader: R) -> io::Result<E::Fr> {
+    let mut bytes = [0u8; 32];
+    reader.read_exact(&mut bytes)?;
+    bytes.reverse();
+
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.read_be(&bytes[..])?;
+
+    E::Fr::from_repr(repr).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, "node is not in field")
+    })
+}
+
+fn write_optional_node<E: JubjubEngine, W: Write>(
+    node: &Option<E::Fr>,
+    mut writer: W
+) -> io::Result<()>
+{
+    match *node {
+        Some(ref node) => {
+            writer.write_u8(1)?;
+            write_node::<E, _>(node, writer)
+        },
+        None => writer.write_u8(0)
+    }
+}
+
+fn read_optional_node<E: JubjubEngine, R: Read>(mut reader: R) -> io::Result<Option<E::Fr>> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(read_node::<E, _>(reader)?)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid optional node"))
+    }
+}
+
+/// Supplies the nodes used to complete a partially-filled tree,
+/// falling back to the roots of empty subtrees when exhausted.
+struct PathFiller<E: JubjubEngine> {
+    queue: VecDeque<E::Fr>,
+    empty_roots: Vec<E::Fr>
+}
+
+impl<E: JubjubEngine> PathFiller<E> {
+    fn empty(params: &E::Params) -> Self {
+        PathFiller {
+            queue: VecDeque::new(),
+            empty_roots: empty_roots::<E>(params)
+        }
+    }
+
+    fn next(&mut self, depth: usize) -> E::Fr {
+        match self.queue.pop_front() {
+            Some(node) => node,
+            None => self.empty_roots[depth]
+        }
+    }
+}
+
+/// A Merkle tree of note commitments, storing only the frontier
+/// needed to append further commitments and compute the root.
+pub struct CommitmentTree<E: JubjubEngine> {
+    left: Option<E::Fr>,
+    right: Option<E::Fr>,
+    parents: Vec<Option<E::Fr>>
+}
+
+impl<E: JubjubEngine> Clone for CommitmentTree<E> {
+    fn clone(&self) -> Self {
+        CommitmentTree {
+            left: self.left,
+            right: self.right,
+            parents: self.parents.clone()
+        }
+    }
+}
+
+impl<E: JubjubEngine> CommitmentTree<E> {
+    /// Creates an empty tree.
+    pub fn new() -> Self {
+        CommitmentTree {
+            left: None,
+            right: None,
+            parents: vec![]
+        }
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let left = read_optional_node::<E, _>(&mut reader)?;
+        let right = read_optional_node::<E, _>(&mut reader)?;
+
+        if left.is_none() && right.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "right leaf without left leaf"));
+        }
+
+        let num_parents = reader.read_u64::<LittleEndian>()?;
+        if num_parents >= SAPLING_COMMITMENT_TREE_DEPTH as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too many parents"));
+        }
+
+        let mut parents = Vec::with_capacity(num_parents as usize);
+        for _ in 0..num_parents {
+            parents.push(read_optional_node::<E, _>(&mut reader)?);
+        }
+
+        Ok(CommitmentTree {
+            left: left,
+            right: right,
+            parents: parents
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_optional_node::<E, _>(&self.left, &mut writer)?;
+        write_optional_node::<E, _>(&self.right, &mut writer)?;
+
+        writer.write_u64::<LittleEndian>(self.parents.len() as u64)?;
+        for parent in &self.parents {
+            write_optional_node::<E, _>(parent, &mut writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of leaves in the tree.
+    pub fn size(&self) -> usize {
+        let leaves = match (self.left, self.right) {
+            (None, None) => 0,
+            (Some(_), None) => 1,
+            (Some(_), Some(_)) => 2,
+            (None, Some(_)) => unreachable!()
+        };
+
+        self.parents.iter().enumerate().fold(leaves, |acc, (i, p)| {
+            if p.is_some() {
+                acc + (1 << (i + 1))
+            } else {
+                acc
+            }
+        })
+    }
+
+    fn is_complete(&self, depth: usize) -> bool {
+        self.left.is_some() &&
+        self.right.is_some() &&
+        self.parents.len() == depth - 1 &&
+        self.parents.iter().all(|p| p.is_some())
+    }
+
+    /// Adds a note commitment to the tree. Returns an error if the
+    /// tree is full.
+    pub fn append(&mut self, node: E::Fr, params: &E::Params) -> Result<(), ()> {
+        self.append_inner(node, SAPLING_COMMITMENT_TREE_DEPTH, params)
+    }
+
+    fn append_inner(
+        &mut self,
+        node: E::Fr,
+        depth: usize,
+        params: &E::Params
+    ) -> Result<(), ()>
+    {
+        if self.is_complete(depth) {
+            // Tree is full
+            return Err(());
+        }
+
+        match (self.left, self.right) {
+            (None, _) => self.left = Some(node),
+            (_, None) => self.right = Some(node),
+            (Some(l), Some(r)) => {
+                let mut combined = merkle_hash::<E>(0, &l, &r, params);
+                self.left = Some(node);
+                self.right = None;
+
+                for i in 0..depth {
+                    if i < self.parents.len() {
+                        let parent = self.parents[i];
+
+                        match parent {
+                            Some(p) => {
+                                combined = merkle_hash::<E>(i + 1, &p, &combined, params);
+                                self.parents[i] = None;
+                            },
+                            None => {
+                                self.parents[i] = Some(combined);
+                                break;
+                            }
+                        }
+                    } else {
+                        self.parents.push(Some(combined));
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current root of the tree, which is the anchor
+    /// consumed by the `Spend` circuit.
+    pub fn root(&self, params: &E::Params) -> E::Fr {
+        self.root_inner(SAPLING_COMMITMENT_TREE_DEPTH, PathFiller::empty(params), params)
+    }
+
+    fn root_inner(
+        &self,
+        depth: usize,
+        mut filler: PathFiller<E>,
+        params: &E::Params
+    ) -> E::Fr
+    {
+        assert!(depth > 0);
+
+        // 1) Hash left and right leaves together, using empty
+        //    leaves as needed.
+        let left = match self.left {
+            Some(node) => node,
+            None => filler.next(0)
+        };
+        let right = match self.right {
+            Some(node) => node,
+            None => filler.next(0)
+        };
+        let mut root = merkle_hash::<E>(0, &left, &right, params);
+
+        // 2) Hash in parents up to the currently-filled depth.
+        for (i, p) in self.parents.iter().enumerate() {
+            root = match *p {
+                Some(ref node) => merkle_hash::<E>(i + 1, node, &root, params),
+                None => merkle_hash::<E>(i + 1, &root, &filler.next(i + 1), params)
+            };
+        }
+
+        // 3) Hash in empty roots up to the fixed depth.
+        for d in (self.parents.len() + 1)..depth {
+            root = merkle_hash::<E>(d, &root, &filler.next(d), params);
+        }
+
+        root
+    }
+}
+
+/// An authentication path from a leaf to the root of the tree,
+/// in the form consumed by the `Spend` circuit: each element is
+/// the sibling at that depth and whether the current subtree is
+/// the right child.
+pub struct CommitmentTreePath<E: JubjubEngine> {
+    pub auth_path: Vec<Option<(E::Fr, bool)>>,
+    pub position: u64
+}
+
+impl<E: JubjubEngine> Clone for CommitmentTreePath<E> {
+    fn clone(&self) -> Self {
+        CommitmentTreePath {
+            auth_path: self.auth_path.clone(),
+            position: self.position
+        }
+    }
+}
+
+impl<E: JubjubEngine> CommitmentTreePath<E> {
+    /// Computes the root of the tree given the leaf this path
+    /// authenticates.
+    pub fn root(&self, leaf: E::Fr, params: &E::Params) -> E::Fr {
+        self.auth_path.iter().enumerate().fold(leaf, |cur, (i, e)| {
+            let (sibling, cur_is_right) = e.expect("auth path is complete");
+
+            if cur_is_right {
+                merkle_hash::<E>(i, &sibling, &cur, params)
+            } else {
+                merkle_hash::<E>(i, &cur, &sibling, params)
+            }
+        })
+    }
+}
+
+/// An updatable witness to the position of a particular note
+/// commitment in the tree. Commitments appended to the tree after
+/// the witnessed one must also be appended to the witness.
+pub struct IncrementalWitness<E: JubjubEngine> {
+    tree: CommitmentTree<E>,
+    filled: Vec<E::Fr>,
+    cursor_depth: usize,
+    cursor: Option<CommitmentTree<E>>
+}
+
+impl<E: JubjubEngine> Clone for IncrementalWitness<E> {
+    fn clone(&self) -> Self {
+        IncrementalWitness {
+            tree: self.tree.clone(),
+            filled: self.filled.clone(),
+            cursor_depth: self.cursor_depth,
+            cursor: self.cursor.clone()
+        }
+    }
+}
+
+impl<E: JubjubEngine> IncrementalWitness<E> {
+    /// Creates a witness to the most recently appended commitment
+    /// of the given tree.
+    pub fn from_tree(tree: &CommitmentTree<E>) -> Self {
+        IncrementalWitness {
+            tree: tree.clone(),
+            filled: vec![],
+            cursor_depth: 0,
+            cursor: None
+        }
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let tree = CommitmentTree::read(&mut reader)?;
+
+        let num_filled = reader.read_u64::<LittleEndian>()?;
+        if num_filled > SAPLING_COMMITMENT_TREE_DEPTH as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too many filled nodes"));
+        }
+
+        let mut filled = Vec::with_capacity(num_filled as usize);
+        for _ in 0..num_filled {
+            filled.push(read_node::<E, _>(&mut reader)?);
+        }
+
+        let cursor = match reader.read_u8()? {
+            0 => None,
+            1 => Some(CommitmentTree::read(&mut reader)?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid optional cursor"))
+        };
+
+        let mut witness = IncrementalWitness {
+            tree: tree,
+            filled: filled,
+            cursor_depth: 0,
+            cursor: cursor
+        };
+
+        witness.cursor_depth = witness.next_depth();
+
+        Ok(witness)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.tree.write(&mut writer)?;
+
+        writer.write_u64::<LittleEndian>(self.filled.len() as u64)?;
+        for node in &self.filled {
+            write_node::<E, _>(node, &mut writer)?;
+        }
+
+        match self.cursor {
+            Some(ref cursor) => {
+                writer.write_u8(1)?;
+                cursor.write(&mut writer)
+            },
+            None => writer.write_u8(0)
+        }
+    }
+
+    /// Returns the position of the witnessed leaf in the tree, or
+    /// `None` if the witness was created from an empty tree.
+    pub fn position(&self) -> Option<u64> {
+        self.tree.size().checked_sub(1).map(|p| p as u64)
+    }
+
+    fn filler(&self, params: &E::Params) -> PathFiller<E> {
+        let cursor_root = self.cursor.as_ref().map(|c| {
+            c.root_inner(self.cursor_depth, PathFiller::empty(params), params)
+        });
+
+        let mut filler = PathFiller::empty(params);
+        filler.queue.extend(self.filled.iter().cloned());
+        filler.queue.extend(cursor_root);
+
+        filler
+    }
+
+    /// Finds the next "depth" of an unfilled subtree.
+    fn next_depth(&self) -> usize {
+        let mut skip = self.filled.len();
+
+        if self.tree.left.is_none() {
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                return 0;
+            }
+        }
+
+        if self.tree.right.is_none() {
+            if skip > 0 {
+                skip -= 1;
+            } else {
+                return 0;
+            }
+        }
+
+        let mut d = 1;
+        for p in &self.tree.parents {
+            if p.is_none() {
+                if skip > 0 {
+                    skip -= 1;
+                } else {
+                    return d;
+                }
+            }
+            d += 1;
+        }
+
+        d + skip
+    }
+
+    /// Tracks a note commitment appended to the tree after the
+    /// witnessed one. Returns an error if the tree is full.
+    pub fn append(&mut self, node: E::Fr, params: &E::Params) -> Result<(), ()> {
+        let depth = SAPLING_COMMITMENT_TREE_DEPTH;
+
+        if let Some(mut cursor) = self.cursor.take() {
+            cursor.append_inner(node, depth, params).expect("cursor should not be full");
+
+            if cursor.is_complete(self.cursor_depth) {
+                let root = cursor.root_inner(self.cursor_depth, PathFiller::empty(params), params);
+                self.filled.push(root);
+            } else {
+                self.cursor = Some(cursor);
+            }
+        } else {
+            self.cursor_depth = self.next_depth();
+
+            if self.cursor_depth >= depth {
+                // Tree is full
+                return Err(());
+            }
+
+            if self.cursor_depth == 0 {
+                self.filled.push(node);
+            } else {
+                let mut cursor = CommitmentTree::new();
+                cursor.append_inner(node, depth, params).expect("cursor should not be full");
+                self.cursor = Some(cursor);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the current root of the tree this witness tracks.
+    pub fn root(&self, params: &E::Params) -> E::Fr {
+        self.tree.root_inner(SAPLING_COMMITMENT_TREE_DEPTH, self.filler(params), params)
+    }
+
+    /// Returns the authentication path of the witnessed commitment,
+    /// or `None` if the witness tracks an empty tree.
+    pub fn path(&self, params: &E::Params) -> Option<CommitmentTreePath<E>> {
+        let mut filler = self.filler(params);
+        let mut auth_path = Vec::with_capacity(SAPLING_COMMITMENT_TREE_DEPTH);
+
+        match (self.tree.left, self.tree.right) {
+            (Some(left), Some(_)) => {
+                // The witnessed leaf is the right child.
+                auth_path.push(Some((left, true)));
+            },
+            (Some(_), None) => {
+                auth_path.push(Some((filler.next(0), false)));
+            },
+            (None, _) => {
+                return None;
+            }
+        }
+
+        for (i, p) in self.tree.parents.iter().enumerate() {
+            auth_path.push(Some(match *p {
+                Some(node) => (node, true),
+                None => (filler.next(i + 1), false)
+            }));
+        }
+
+        for d in (self.tree.parents.len() + 1)..SAPLING_COMMITMENT_TREE_DEPTH {
+            auth_path.push(Some((filler.next(d), false)));
+        }
+
+        assert_eq!(auth_path.len(), SAPLING_COMMITMENT_TREE_DEPTH);
+
+        Some(CommitmentTreePath {
+            auth_path: auth_path,
+            position: self.position()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::{Bls12, Fr};
+    use jubjub::{JubjubBls12, fs, edwards};
+    use pedersen_hash;
+    use super::*;
+
+    #[test]
+    fn test_merkle_hash() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for depth in 0..SAPLING_COMMITMENT_TREE_DEPTH {
+            let nodes: Vec<Fr> = (0..8).map(|_| rng.gen()).collect();
+
+            let layer = merkle_hash_layer::<Bls12>(depth, &nodes, params);
+            assert_eq!(layer.len(), 4);
+
+            for (pair, parent) in nodes.chunks(2).zip(layer.iter()) {
+                assert_eq!(merkle_hash::<Bls12>(depth, &pair[0], &pair[1], params), *parent);
+
+                // The lookup tables agree with the plain Pedersen hash.
+                let expected = pedersen_hash::pedersen_hash::<Bls12, _>(
+                    Personalization::MerkleTree(depth),
+                    merkle_hash_bits::<Bls12>(&pair[0], &pair[1]),
+                    params
+                ).into_xy().0;
+                assert_eq!(expected, *parent);
+            }
+        }
+
+        assert!(merkle_hash_layer::<Bls12>(0, &[], params).is_empty());
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let params = &JubjubBls12::new();
+
+        let tree = CommitmentTree::<Bls12>::new();
+        assert_eq!(tree.size(), 0);
+        assert_eq!(tree.root(params), empty_roots::<Bls12>(params)[SAPLING_COMMITMENT_TREE_DEPTH]);
+
+        let witness = IncrementalWitness::from_tree(&tree);
+        assert_eq!(witness.position(), None);
+        assert!(witness.path(params).is_none());
+    }
+
+    #[test]
+    fn test_incremental_witnesses() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        let mut witnesses: Vec<(IncrementalWitness<Bls12>, Fr)> = vec![];
+
+        for i in 0..6 {
+            let leaf: Fr = rng.gen();
+
+            tree.append(leaf, params).unwrap();
+            for &mut (ref mut witness, _) in witnesses.iter_mut() {
+                witness.append(leaf, params).unwrap();
+            }
+            witnesses.push((IncrementalWitness::from_tree(&tree), leaf));
+
+            assert_eq!(tree.size(), i + 1);
+
+            let root = tree.root(params);
+
+            for (position, &(ref witness, leaf)) in witnesses.iter().enumerate() {
+                assert_eq!(witness.position(), Some(position as u64));
+                assert_eq!(witness.root(params), root);
+
+                let path = witness.path(params).unwrap();
+                assert_eq!(path.position, position as u64);
+                assert_eq!(path.root(leaf, params), root);
+
+                // The position bits of the path encode the position.
+                for (d, e) in path.auth_path.iter().enumerate() {
+                    assert_eq!(e.unwrap().1, (position >> d) & 1 == 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        tree.append(rng.gen(), params).unwrap();
+        tree.append(rng.gen(), params).unwrap();
+        tree.append(rng.gen(), params).unwrap();
+
+        let mut witness = IncrementalWitness::from_tree(&tree);
+        for _ in 0..3 {
+            let leaf = rng.gen();
+            tree.append(leaf, params).unwrap();
+            witness.append(leaf, params).unwrap();
+        }
+
+        let mut tree_bytes = vec![];
+        tree.write(&mut tree_bytes).unwrap();
+        let tree2 = CommitmentTree::<Bls12>::read(&tree_bytes[..]).unwrap();
+        assert_eq!(tree2.size(), tree.size());
+        assert_eq!(tree2.root(params), tree.root(params));
+
+        let mut witness_bytes = vec![];
+        witness.write(&mut witness_bytes).unwrap();
+        let mut witness2 = IncrementalWitness::<Bls12>::read(&witness_bytes[..]).unwrap();
+        assert_eq!(witness2.position(), witness.position());
+        assert_eq!(witness2.root(params), witness.root(params));
+
+        // The deserialized witness can continue to be updated.
+        let leaf = rng.gen();
+        tree.append(leaf, params).unwrap();
+        witness2.append(leaf, params).unwrap();
+        assert_eq!(witness2.root(params), tree.root(params));
+
+        // Non-canonical nodes are rejected.
+        let mut bad_bytes = tree_bytes.clone();
+        for b in &mut bad_bytes[1..33] {
+            *b = 0xff;
+        }
+        assert!(CommitmentTree::<Bls12>::read(&bad_bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_anchor_matches_spend_circuit() {
+        use bellman::Circuit;
+        use circuit::test::TestConstraintSystem;
+        use circuit::sapling::Spend;
+        use primitives::{Diversifier, ProofGenerationKey, ValueCommitment};
+
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let proof_generation_key = ProofGenerationKey::<Bls12> {
+            ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+            rsk: rng.gen()
+        };
+        let viewing_key = proof_generation_key.into_viewing_key(params);
+
+        let payment_address = loop {
+            let diversifier = Diversifier(rng.gen());
+
+            if let Ok(p) = viewing_key.into_payment_address(diversifier, params) {
+                break p;
+            }
+        };
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: rng.gen(),
+            randomness: rng.gen()
+        };
+        let commitment_randomness: fs::Fs = rng.gen();
+        let note = payment_address.create_note(
+            value_commitment.value,
+            commitment_randomness,
+            params
+        ).unwrap();
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        for _ in 0..3 {
+            tree.append(rng.gen(), params).unwrap();
+        }
+        tree.append(note.cm(params), params).unwrap();
+        let mut witness = IncrementalWitness::from_tree(&tree);
+        for _ in 0..2 {
+            let leaf = rng.gen();
+            tree.append(leaf, params).unwrap();
+            witness.append(leaf, params).unwrap();
+        }
+
+        let path = witness.path(params).unwrap();
+        let anchor = tree.root(params);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let instance = Spend {
+            params: params,
+            value_commitment: Some(value_commitment),
+            proof_generation_key: Some(proof_generation_key),
+            payment_address: Some(payment_address),
+            commitment_randomness: Some(commitment_randomness),
+            auth_path: path.auth_path.clone()
+        };
+
+        instance.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.get_input(3, "anchor/input variable"), anchor);
+
+        let expected_nf = note.nf(&viewing_key, path.position, params).into_xy();
+        assert_eq!(cs.get_input(4, "nullifier/x/input variable"), expected_nf.0);
+        assert_eq!(cs.get_input(5, "nullifier/y/input variable"), expected_nf.1);
+    }
+}
@@ -0,0 +1,406 @@
+//! Encryption of note plaintexts to the recipient of an `Output`.
+//!
+//! The sender picks an ephemeral secret `esk` and publishes
+//! `epk = [esk] g_d`, which is exactly the `epk` exposed by the `Output`
+//! circuit. Both parties can then agree on the shared secret
+//! `[h_J esk] pk_d = [h_J ivk] epk`, from which the symmetric keys are
+//! derived. The note plaintext is encrypted with a BLAKE2b keystream and
+//! authenticated with a keyed BLAKE2b tag, so that a recipient scanning
+//! the chain with the wrong incoming viewing key learns nothing.
+//!
+//! This format is specific to this crate: it is not the ZIP note
+//! encryption, and its ciphertexts are not compatible with it.
+
+use pairing::Field;
+
+use blake2_rfc::blake2b::Blake2b;
+
+use byteorder::{
+    LittleEndian,
+    ReadBytesExt,
+    WriteBytesExt
+};
+
+use rand::Rng;
+
+use constants;
+
+use error::Error;
+
+use jubjub::{
+    JubjubEngine,
+    PrimeOrder,
+    Unknown,
+    edwards
+};
+
+use primitives::{
+    Diversifier,
+    IncomingViewingKey,
+    Note,
+    PaymentAddress
+};
+
+use util::{
+    read_scalar,
+    write_scalar,
+    zeroize
+};
+
+/// The length of a memo field.
+pub const MEMO_SIZE: usize = 512;
+
+/// The length of an encoded note plaintext:
+/// the diversifier, value, commitment randomness and memo.
+pub const NOTE_PLAINTEXT_SIZE: usize = 11 + 8 + 32 + MEMO_SIZE;
+
+/// The length of the authentication tag appended to the ciphertext.
+pub const NOTE_ENCRYPTION_TAG_SIZE: usize = 16;
+
+/// The length of an encrypted note plaintext.
+pub const ENC_CIPHERTEXT_SIZE: usize = NOTE_PLAINTEXT_SIZE + NOTE_ENCRYPTION_TAG_SIZE;
+
+/// An arbitrary message attached to a note for its recipient.
+pub struct Memo(pub [u8; MEMO_SIZE]);
+
+impl Default for Memo {
+    /// The empty memo, which begins with 0xF6 and is otherwise
+    /// zero, as in Sprout.
+    fn default() -> Self {
+        let mut memo = [0u8; MEMO_SIZE];
+        memo[0] = 0xF6;
+
+        Memo(memo)
+    }
+}
+
+impl PartialEq for Memo {
+    fn eq(&self, other: &Memo) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+/// The symmetric keys derived from the shared secret.
+struct SymmetricKeys {
+    enc_key: [u8; 32],
+    mac_key: [u8; 32]
+}
+
+/// Derives the symmetric keys from the Diffie-Hellman secret and the
+/// ephemeral public key.
+fn kdf<E: JubjubEngine>(
+    dhsecret: &edwards::Point<E, PrimeOrder>,
+    epk: &edwards::Point<E, PrimeOrder>
+) -> SymmetricKeys
+{
+    let mut input = [0u8; 64];
+    dhsecret.write(&mut input[0..32]).unwrap();
+    epk.write(&mut input[32..64]).unwrap();
+
+    let mut h = Blake2b::with_params(64, &[], &[], constants::NOTE_ENCRYPTION_KDF_PERSONALIZATION);
+    h.update(&input);
+    let h = h.finalize();
+
+    let mut keys = SymmetricKeys {
+        enc_key: [0u8; 32],
+        mac_key: [0u8; 32]
+    };
+    keys.enc_key.copy_from_slice(&h.as_ref()[0..32]);
+    keys.mac_key.copy_from_slice(&h.as_ref()[32..64]);
+
+    keys
+}
+
+/// XORs `buf` with the BLAKE2b keystream for `key`. Each 64-byte block
+/// of the keystream is the keyed hash of its little endian index.
+fn apply_keystream(key: &[u8; 32], buf: &mut [u8]) {
+    for (i, chunk) in buf.chunks_mut(64).enumerate() {
+        let mut counter = [0u8; 8];
+        (&mut counter[..]).write_u64::<LittleEndian>(i as u64).unwrap();
+
+        let mut h = Blake2b::with_params(64, key, &[], constants::NOTE_ENCRYPTION_STREAM_PERSONALIZATION);
+        h.update(&counter);
+        let h = h.finalize();
+
+        for (b, k) in chunk.iter_mut().zip(h.as_ref().iter()) {
+            *b ^= *k;
+        }
+    }
+}
+
+/// Computes the authentication tag over a ciphertext.
+fn mac(key: &[u8; 32], ciphertext: &[u8]) -> [u8; NOTE_ENCRYPTION_TAG_SIZE] {
+    let mut h = Blake2b::with_params(NOTE_ENCRYPTION_TAG_SIZE, key, &[], constants::NOTE_ENCRYPTION_MAC_PERSONALIZATION);
+    h.update(ciphertext);
+    let h = h.finalize();
+
+    let mut tag = [0u8; NOTE_ENCRYPTION_TAG_SIZE];
+    tag.copy_from_slice(h.as_ref());
+
+    tag
+}
+
+/// Compares two byte strings without branching on their contents.
+fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b.iter()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// The sender's side of the encryption of a note to its recipient.
+pub struct NoteEncryption<E: JubjubEngine> {
+    epk: edwards::Point<E, PrimeOrder>,
+    esk: E::Fs,
+    diversifier: Diversifier,
+    note: Note<E>,
+    memo: Memo
+}
+
+impl<E: JubjubEngine> Drop for NoteEncryption<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.esk, E::Fs::zero());
+    }
+}
+
+impl<E: JubjubEngine> NoteEncryption<E> {
+    /// Prepares the encryption of `note`, which is sent to the address
+    /// with the given diversifier, picking a fresh ephemeral secret.
+    ///
+    /// Returns `Error::InvalidDiversifier` if the diversifier does not
+    /// correspond to the note's `g_d`, as the recipient could not
+    /// decrypt the ciphertext.
+    pub fn new<R: Rng>(
+        diversifier: Diversifier,
+        note: Note<E>,
+        memo: Memo,
+        rng: &mut R,
+        params: &E::Params
+    ) -> Result<Self, Error>
+    {
+        if diversifier.g_d::<E>(params)? != note.g_d {
+            return Err(Error::InvalidDiversifier);
+        }
+
+        let esk: E::Fs = rng.gen();
+        let epk = note.g_d.mul_secret(esk, params);
+
+        Ok(NoteEncryption {
+            epk: epk,
+            esk: esk,
+            diversifier: diversifier,
+            note: note,
+            memo: memo
+        })
+    }
+
+    /// The ephemeral secret key, which is witnessed by the `Output`
+    /// circuit.
+    pub fn esk(&self) -> &E::Fs {
+        &self.esk
+    }
+
+    /// The ephemeral public key, which is exposed by the `Output`
+    /// circuit and published alongside the ciphertext.
+    pub fn epk(&self) -> &edwards::Point<E, PrimeOrder> {
+        &self.epk
+    }
+
+    /// Encrypts the note plaintext and memo to the recipient.
+    pub fn encrypt_note_plaintext(&self, params: &E::Params) -> Vec<u8> {
+        let pk_d: edwards::Point<E, Unknown> = self.note.pk_d.mul_secret(self.esk, params).into();
+        let dhsecret = pk_d.mul_by_cofactor(params);
+        let keys = kdf(&dhsecret, &self.epk);
+
+        let mut output = Vec::with_capacity(ENC_CIPHERTEXT_SIZE);
+        output.extend_from_slice(&self.diversifier.0);
+        output.write_u64::<LittleEndian>(self.note.value).unwrap();
+        write_scalar::<E::Fs, _>(&self.note.r, &mut output).unwrap();
+        output.extend_from_slice(&self.memo.0);
+        assert_eq!(output.len(), NOTE_PLAINTEXT_SIZE);
+
+        apply_keystream(&keys.enc_key, &mut output);
+
+        let tag = mac(&keys.mac_key, &output);
+        output.extend_from_slice(&tag);
+
+        output
+    }
+}
+
+/// Trial-decrypts a note ciphertext with the incoming viewing key `ivk`.
+///
+/// Returns the note, the address it was sent to and its memo if the
+/// ciphertext was encrypted to an address derived from `ivk` and the
+/// decrypted note matches the note commitment `cmu`, and `None` otherwise.
+pub fn try_note_decryption<E: JubjubEngine>(
+    ivk: &IncomingViewingKey<E>,
+    epk: &edwards::Point<E, Unknown>,
+    cmu: &E::Fr,
+    ciphertext: &[u8],
+    params: &E::Params
+) -> Option<(Note<E>, PaymentAddress<E>, Memo)>
+{
+    if ciphertext.len() != ENC_CIPHERTEXT_SIZE {
+        return None;
+    }
+
+    // The epk must be in the prime order subgroup, as in the Output
+    // circuit, so that the KDF input is the same for both parties.
+    let epk = match epk.as_prime_order(params) {
+        Some(epk) => epk,
+        None => return None
+    };
+
+    let dhsecret = epk.mul_secret(ivk.0, params);
+    let dhsecret: edwards::Point<E, Unknown> = dhsecret.into();
+    let dhsecret = dhsecret.mul_by_cofactor(params);
+    let keys = kdf(&dhsecret, &epk);
+
+    let (ciphertext, tag) = ciphertext.split_at(NOTE_PLAINTEXT_SIZE);
+    if !ct_eq(&mac(&keys.mac_key, ciphertext), tag) {
+        return None;
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    apply_keystream(&keys.enc_key, &mut plaintext);
+
+    let mut d = [0u8; 11];
+    d.copy_from_slice(&plaintext[0..11]);
+    let diversifier = Diversifier(d);
+
+    let value = (&plaintext[11..19]).read_u64::<LittleEndian>().unwrap();
+
+    let r = match read_scalar::<E::Fs, _>(&plaintext[19..51]) {
+        Ok(r) => r,
+        Err(_) => return None
+    };
+
+    let mut memo = [0u8; MEMO_SIZE];
+    memo.copy_from_slice(&plaintext[51..NOTE_PLAINTEXT_SIZE]);
+
+    let to = match ivk.into_payment_address(diversifier, params) {
+        Ok(to) => to,
+        Err(_) => return None
+    };
+
+    let note = match to.create_note(value, r, params) {
+        Ok(note) => note,
+        Err(_) => return None
+    };
+
+    if note.cm(params) != *cmu {
+        return None;
+    }
+
+    Some((note, to, Memo(memo)))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::{Bls12, Fr};
+    use pairing::Field;
+    use jubjub::{JubjubBls12, Unknown, edwards, fs};
+    use primitives::{Diversifier, ProofGenerationKey, ViewingKey};
+    use super::*;
+
+    fn random_viewing_key<R: Rng>(rng: &mut R, params: &JubjubBls12) -> ViewingKey<Bls12> {
+        ProofGenerationKey::<Bls12> {
+            ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+            rsk: rng.gen()
+        }.into_viewing_key(params)
+    }
+
+    fn random_address<R: Rng>(
+        viewing_key: &ViewingKey<Bls12>,
+        rng: &mut R,
+        params: &JubjubBls12
+    ) -> PaymentAddress<Bls12>
+    {
+        loop {
+            let diversifier = Diversifier(rng.gen());
+            if let Ok(to) = viewing_key.into_payment_address(diversifier, params) {
+                return to;
+            }
+        }
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let viewing_key = random_viewing_key(rng, params);
+            let ivk = viewing_key.ivk();
+            let to = random_address(&viewing_key, rng, params);
+
+            let value = rng.gen();
+            let r: fs::Fs = rng.gen();
+            let note = to.create_note(value, r, params).unwrap();
+            let cmu = note.cm(params);
+
+            let mut memo = Memo::default();
+            memo.0[0..5].copy_from_slice(b"hello");
+
+            // The diversifier must match the note.
+            let other = random_address(&viewing_key, rng, params);
+            let other_note = to.create_note(value, r, params).unwrap();
+            match NoteEncryption::new(other.diversifier, other_note, Memo::default(), rng, params) {
+                Err(Error::InvalidDiversifier) => {},
+                _ => panic!("mismatched diversifier should be rejected")
+            }
+
+            let ne = NoteEncryption::new(to.diversifier, note, memo, rng, params).unwrap();
+            let epk: edwards::Point<Bls12, Unknown> = ne.epk().clone().into();
+            let ciphertext = ne.encrypt_note_plaintext(params);
+            assert_eq!(ciphertext.len(), ENC_CIPHERTEXT_SIZE);
+
+            // epk is computed as in the Output circuit.
+            let g_d = to.g_d(params).unwrap();
+            assert!(*ne.epk() == g_d.mul(*ne.esk(), params));
+
+            let (note, to_2, memo) = try_note_decryption(&ivk, &epk, &cmu, &ciphertext, params).unwrap();
+            assert_eq!(note.value, value);
+            assert_eq!(note.r, r);
+            assert!(note.pk_d == to.pk_d);
+            assert!(to_2.pk_d == to.pk_d);
+            assert_eq!(to_2.diversifier.0, to.diversifier.0);
+            assert!(&memo.0[0..5] == b"hello");
+            assert!(memo.0[5..].iter().all(|b| *b == 0));
+
+            // The wrong incoming viewing key cannot decrypt.
+            let other_ivk = random_viewing_key(rng, params).ivk();
+            assert!(try_note_decryption(&other_ivk, &epk, &cmu, &ciphertext, params).is_none());
+
+            // The wrong epk cannot decrypt.
+            let other_epk: edwards::Point<Bls12, Unknown> = edwards::Point::rand(rng, params).mul_by_cofactor(params).into();
+            assert!(try_note_decryption(&ivk, &other_epk, &cmu, &ciphertext, params).is_none());
+
+            // The note must match the commitment.
+            let mut other_cmu = cmu;
+            other_cmu.add_assign(&Fr::one());
+            assert!(try_note_decryption(&ivk, &epk, &other_cmu, &ciphertext, params).is_none());
+
+            // Any modification to the ciphertext is detected.
+            for i in &[0, 20, NOTE_PLAINTEXT_SIZE - 1, ENC_CIPHERTEXT_SIZE - 1] {
+                let mut tampered = ciphertext.clone();
+                tampered[*i] ^= 0x01;
+                assert!(try_note_decryption(&ivk, &epk, &cmu, &tampered, params).is_none());
+            }
+
+            // Truncated ciphertexts are rejected.
+            assert!(try_note_decryption(&ivk, &epk, &cmu, &ciphertext[1..], params).is_none());
+        }
+    }
+
+    #[test]
+    fn test_default_memo() {
+        let memo = Memo::default();
+        assert_eq!(memo.0[0], 0xF6);
+        assert!(memo.0[1..].iter().all(|b| *b == 0));
+        assert!(memo == Memo::default());
+    }
+}
@@ -1,4 +1,5 @@
 use jubjub::*;
+use jubjub::multiexp::multiexp;
 use pairing::*;
 
 pub enum Personalization {
@@ -20,6 +21,10 @@ impl Personalization {
     }
 }
 
+/// Below this many generators, multiplying each generator separately
+/// is faster than the bucket method of `multiexp`.
+const MULTIEXP_THRESHOLD: usize = 32;
+
 pub fn pedersen_hash<E, I>(
     personalization: Personalization,
     bits: I,
@@ -30,8 +35,7 @@ pub fn pedersen_hash<E, I>(
 {
     let mut bits = personalization.get_bits().into_iter().chain(bits.into_iter());
 
-    let mut result = edwards::Point::zero();
-    let mut generators = params.pedersen_hash_generators().iter();
+    let mut scalars = vec![];
 
     loop {
         let mut acc = E::Fs::zero();
@@ -78,10 +82,89 @@ pub fn pedersen_hash<E, I>(
             break;
         }
 
-        let mut tmp = generators.next().expect("we don't have enough generators").clone();
-        tmp = tmp.mul(acc, params);
-        result = result.add(&tmp, params);
+        scalars.push(acc.into_repr());
+    }
+
+    let generators = params.pedersen_hash_generators();
+    assert!(scalars.len() <= generators.len(), "we don't have enough generators");
+
+    if scalars.len() < MULTIEXP_THRESHOLD {
+        scalars.iter().zip(generators.iter()).fold(edwards::Point::zero(), |acc, (scalar, g)| {
+            acc.add(&g.mul(*scalar, params), params)
+        })
+    } else {
+        multiexp(&generators[0..scalars.len()], &scalars, params)
+    }
+}
+
+/// Computes the same hash as `pedersen_hash` with a single mixed
+/// addition per 3-bit chunk, looking up each chunk's multiple of
+/// its generator in `JubjubParams::pedersen_hash_exp_table`.
+pub fn pedersen_hash_precomputed<E, I>(
+    personalization: Personalization,
+    bits: I,
+    params: &E::Params
+) -> edwards::Point<E, PrimeOrder>
+    where I: IntoIterator<Item=bool>,
+          E: JubjubEngine
+{
+    let mut bits = personalization.get_bits().into_iter().chain(bits.into_iter());
+
+    let mut result = edwards::Point::zero();
+    let mut windows = params.pedersen_hash_exp_table().iter().flat_map(|segment| segment.iter());
+
+    // Grab three bits from the input
+    while let Some(a) = bits.next() {
+        let b = bits.next().unwrap_or(false);
+        let c = bits.next().unwrap_or(false);
+
+        let window = windows.next().expect("we don't have enough generators");
+
+        // The chunk is (1 + a + 2b), negated if c is set
+        let p = &window[(a as usize) + 2 * (b as usize)];
+
+        result = if c {
+            result.add_mixed(&p.negate(), params)
+        } else {
+            result.add_mixed(p, params)
+        };
     }
 
     result
 }
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, JubjubParams};
+    use super::*;
+
+    #[test]
+    fn test_pedersen_hash_precomputed() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let max_bits = params.pedersen_hash_generators().len()
+                       * params.pedersen_hash_chunks_per_generator() * 3 - 6;
+
+        for &len in &[0, 1, 2, 3, 4, 182, 183, 184, 185, 186, 187, 510, max_bits] {
+            for _ in 0..5 {
+                let bits: Vec<bool> = (0..len).map(|_| rng.gen()).collect();
+
+                assert!(
+                    pedersen_hash::<Bls12, _>(Personalization::NoteCommitment, bits.clone(), params)
+                    ==
+                    pedersen_hash_precomputed::<Bls12, _>(Personalization::NoteCommitment, bits.clone(), params)
+                );
+
+                let depth = rng.gen_range(0, 32);
+                assert!(
+                    pedersen_hash::<Bls12, _>(Personalization::MerkleTree(depth), bits.clone(), params)
+                    ==
+                    pedersen_hash_precomputed::<Bls12, _>(Personalization::MerkleTree(depth), bits, params)
+                );
+            }
+        }
+    }
+}
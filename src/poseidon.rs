@@ -0,0 +1,329 @@
+//! The Poseidon algebraic hash function over the scalar field of
+//! an `Engine`, with the x^5 S-box. The state is `width` field
+//! elements; a permutation consists of `full_rounds / 2` full
+//! rounds, `partial_rounds` partial rounds and `full_rounds / 2`
+//! full rounds again. Each round adds the round constants, applies
+//! the S-box (to every element in a full round, to the first
+//! element only in a partial round) and multiplies by the MDS
+//! matrix.
+//!
+//! Hashing uses a sponge with a capacity of one element, which is
+//! initialized to the length of the input, and absorbs `width - 1`
+//! elements per permutation.
+
+use pairing::{
+    Engine,
+    Field,
+    PrimeField
+};
+
+use blake2_rfc::blake2s::Blake2s;
+
+use byteorder::{
+    LittleEndian,
+    WriteBytesExt
+};
+
+use constants;
+use util::reduce_le_bytes;
+
+/// The parameters of a Poseidon permutation: the width of the
+/// state, the number of rounds, the round constants and the MDS
+/// matrix.
+pub struct PoseidonParams<E: Engine> {
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<E::Fr>,
+    mds: Vec<Vec<E::Fr>>
+}
+
+impl<E: Engine> PoseidonParams<E> {
+    /// Derives the parameters for a state of `width` elements.
+    /// The round constants are produced by BLAKE2s from the width
+    /// and the number of rounds, so the same arguments always give
+    /// the same parameters. The MDS matrix is the Cauchy matrix
+    /// `1 / (x_i + y_j)` with `x_i = i` and `y_j = width + j`.
+    ///
+    /// Panics if the width is less than 2, if `full_rounds` is odd,
+    /// or if x^5 is not a permutation of the field. Any other round
+    /// counts are accepted: callers are responsible for choosing ones
+    /// that are secure for the width and field.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2);
+        assert!(full_rounds % 2 == 0);
+
+        // x^5 is a permutation iff gcd(5, p - 1) = 1, that is iff
+        // p != 1 mod 5. As 2^64 = 1 mod 5, p mod 5 is the sum of
+        // its limbs mod 5.
+        let p_mod_5 = E::Fr::char().as_ref().iter().fold(0, |acc, limb| (acc + limb % 5) % 5);
+        assert!(p_mod_5 != 1, "x^5 is not a permutation of the field");
+
+        let num_constants = (full_rounds + partial_rounds) * width;
+        let round_constants = (0..num_constants).map(|i| {
+            generate_constant::<E>(width, full_rounds, partial_rounds, i)
+        }).collect();
+
+        let mds = (0..width).map(|i| {
+            (0..width).map(|j| {
+                E::Fr::from_repr(
+                    <E::Fr as PrimeField>::Repr::from((i + width + j) as u64)
+                ).expect("small integers are in the field")
+                 .inverse().expect("x_i + y_j is nonzero")
+            }).collect()
+        }).collect();
+
+        PoseidonParams {
+            width: width,
+            full_rounds: full_rounds,
+            partial_rounds: partial_rounds,
+            round_constants: round_constants,
+            mds: mds
+        }
+    }
+
+    /// The parameters for a state of 3 elements, which hashes two
+    /// elements per permutation, with 8 full rounds and 57 partial
+    /// rounds. These round counts are the ones recommended by the
+    /// Poseidon paper for x^5 over a 255-bit field at the 128-bit
+    /// security level.
+    pub fn recommended_width_3() -> Self {
+        Self::new(3, 8, 57)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of elements absorbed per permutation.
+    pub fn rate(&self) -> usize {
+        self.width - 1
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    /// Returns whether the S-box is applied to the whole state
+    /// in the given round.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        round < self.full_rounds / 2 || round >= self.full_rounds / 2 + self.partial_rounds
+    }
+
+    /// The constants added to the state at the start of `round`.
+    pub fn round_constants(&self, round: usize) -> &[E::Fr] {
+        &self.round_constants[(round * self.width)..((round + 1) * self.width)]
+    }
+
+    /// The MDS matrix, by rows.
+    pub fn mds(&self) -> &[Vec<E::Fr>] {
+        &self.mds
+    }
+}
+
+/// Hashes the parameters and the index of a round constant into
+/// 64 bytes with BLAKE2s, and reduces them into the field so that
+/// the result is close to uniform.
+fn generate_constant<E: Engine>(
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    index: usize
+) -> E::Fr
+{
+    let mut tag = vec![];
+    tag.write_u32::<LittleEndian>(width as u32).unwrap();
+    tag.write_u32::<LittleEndian>(full_rounds as u32).unwrap();
+    tag.write_u32::<LittleEndian>(partial_rounds as u32).unwrap();
+    tag.write_u64::<LittleEndian>(index as u64).unwrap();
+
+    let mut bytes = [0u8; 64];
+    for (i, half) in bytes.chunks_mut(32).enumerate() {
+        let mut h = Blake2s::with_params(32, &[], &[], constants::POSEIDON_CONSTANTS_PERSONALIZATION);
+        h.update(&tag);
+        h.update(&[i as u8]);
+        half.copy_from_slice(h.finalize().as_ref());
+    }
+
+    reduce_le_bytes(&bytes)
+}
+
+/// Computes x^5.
+fn sbox<F: Field>(x: &mut F) {
+    let mut tmp = *x;
+    tmp.square();
+    tmp.square();
+    x.mul_assign(&tmp);
+}
+
+/// Applies the Poseidon permutation to `state` in place.
+pub fn poseidon_permutation<E: Engine>(
+    state: &mut [E::Fr],
+    params: &PoseidonParams<E>
+)
+{
+    assert_eq!(state.len(), params.width());
+
+    for round in 0..params.num_rounds() {
+        for (s, c) in state.iter_mut().zip(params.round_constants(round)) {
+            s.add_assign(c);
+        }
+
+        if params.is_full_round(round) {
+            for s in state.iter_mut() {
+                sbox(s);
+            }
+        } else {
+            sbox(&mut state[0]);
+        }
+
+        let prev = state.to_vec();
+        for (s, row) in state.iter_mut().zip(params.mds()) {
+            *s = E::Fr::zero();
+            for (m, p) in row.iter().zip(prev.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(p);
+                s.add_assign(&tmp);
+            }
+        }
+    }
+}
+
+/// Hashes a sequence of field elements into one.
+pub fn poseidon_hash<E: Engine>(
+    inputs: &[E::Fr],
+    params: &PoseidonParams<E>
+) -> E::Fr
+{
+    let mut state = vec![E::Fr::zero(); params.width()];
+
+    // Domain separation between inputs of different lengths
+    state[0] = E::Fr::from_repr(
+        <E::Fr as PrimeField>::Repr::from(inputs.len() as u64)
+    ).expect("the length is in the field");
+
+    if inputs.is_empty() {
+        poseidon_permutation(&mut state, params);
+    }
+
+    for chunk in inputs.chunks(params.rate()) {
+        for (s, x) in state[1..].iter_mut().zip(chunk) {
+            s.add_assign(x);
+        }
+
+        poseidon_permutation(&mut state, params);
+    }
+
+    state[1]
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::{Bls12, Fr};
+    use super::*;
+
+    #[test]
+    fn test_params_deterministic() {
+        let a = PoseidonParams::<Bls12>::recommended_width_3();
+        let b = PoseidonParams::<Bls12>::new(3, 8, 57);
+        let c = PoseidonParams::<Bls12>::new(3, 8, 56);
+
+        for round in 0..a.num_rounds() {
+            assert_eq!(a.round_constants(round), b.round_constants(round));
+        }
+
+        // The constants depend on the number of rounds
+        assert!(a.round_constants(0) != c.round_constants(0));
+
+        // The constants are distinct
+        let constants: Vec<Fr> = (0..a.num_rounds()).flat_map(|r| a.round_constants(r).to_vec()).collect();
+        for i in 0..constants.len() {
+            for j in (i + 1)..constants.len() {
+                assert!(constants[i] != constants[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_schedule() {
+        let params = PoseidonParams::<Bls12>::recommended_width_3();
+
+        let full: Vec<usize> = (0..params.num_rounds()).filter(|&r| params.is_full_round(r)).collect();
+        assert_eq!(full, vec![0, 1, 2, 3, 61, 62, 63, 64]);
+    }
+
+    #[test]
+    fn test_mds() {
+        let params = PoseidonParams::<Bls12>::recommended_width_3();
+
+        // Every entry is 1 / (i + 3 + j)
+        for (i, row) in params.mds().iter().enumerate() {
+            for (j, e) in row.iter().enumerate() {
+                let mut tmp = Fr::from_repr(<Fr as PrimeField>::Repr::from((i + 3 + j) as u64)).unwrap();
+                tmp.mul_assign(e);
+                assert_eq!(tmp, Fr::one());
+            }
+        }
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for &width in &[2, 3, 5] {
+            let params = PoseidonParams::<Bls12>::new(width, 8, 57);
+
+            for len in 0..8 {
+                let inputs: Vec<Fr> = (0..len).map(|_| rng.gen()).collect();
+                let hash = poseidon_hash(&inputs, &params);
+
+                assert_eq!(hash, poseidon_hash(&inputs, &params));
+
+                if len > 0 {
+                    // Changing any input changes the hash
+                    let mut other = inputs.clone();
+                    other[len - 1].add_assign(&Fr::one());
+                    assert!(hash != poseidon_hash(&other, &params));
+                }
+
+                // Padding with zero doesn't collide
+                let mut padded = inputs.clone();
+                padded.push(Fr::zero());
+                assert!(hash != poseidon_hash(&padded, &params));
+            }
+        }
+    }
+
+    #[test]
+    fn test_permutation_is_not_linear() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let params = PoseidonParams::<Bls12>::recommended_width_3();
+
+        let a: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+        let b: Vec<Fr> = (0..3).map(|_| rng.gen()).collect();
+        let mut sum: Vec<Fr> = a.iter().zip(b.iter()).map(|(a, b)| {
+            let mut tmp = *a;
+            tmp.add_assign(b);
+            tmp
+        }).collect();
+
+        let mut pa = a.clone();
+        let mut pb = b.clone();
+        poseidon_permutation(&mut pa, &params);
+        poseidon_permutation(&mut pb, &params);
+        poseidon_permutation(&mut sum, &params);
+
+        let mut expected = pa[0];
+        expected.add_assign(&pb[0]);
+        assert!(sum[0] != expected);
+    }
+}
@@ -15,12 +15,13 @@ use pedersen_hash::{
 
 use byteorder::{
     BigEndian,
+    LittleEndian,
+    ReadBytesExt,
     WriteBytesExt
 };
 
 use jubjub::{
     JubjubEngine,
-    JubjubParams,
     edwards,
     PrimeOrder,
     FixedGenerators
@@ -28,77 +29,206 @@ use jubjub::{
 
 use blake2_rfc::blake2s::Blake2s;
 
-#[derive(Clone)]
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use error::Error;
+
+use util::{
+    read_prime_order_point,
+    read_scalar,
+    reduce_le_bytes,
+    write_scalar,
+    zeroize,
+    SecretBuffer
+};
+
+/// Interprets a big endian 256-bit hash as a scalar after dropping
+/// its first five bits. The result is less than 2^251, which is
+/// smaller than the order of the scalar field, so no reduction
+/// takes place.
+fn drop_5_to_scalar<E: JubjubEngine>(h: &[u8]) -> E::Fs {
+    let mut h = h.to_vec();
+    h[0] &= 0b0000_0111;
+    h.reverse();
+
+    reduce_le_bytes::<E::Fs>(&h)
+}
+
 pub struct ValueCommitment<E: JubjubEngine> {
     pub value: u64,
     pub randomness: E::Fs
 }
 
+impl<E: JubjubEngine> Drop for ValueCommitment<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.randomness, E::Fs::zero());
+    }
+}
+
 impl<E: JubjubEngine> ValueCommitment<E> {
+    /// Copies the value and randomness. This is not a `Clone`
+    /// implementation, so that every copy of the randomness is
+    /// deliberate.
+    pub fn clone_secret(&self) -> Self {
+        ValueCommitment {
+            value: self.value,
+            randomness: self.randomness
+        }
+    }
+
     pub fn cm(
         &self,
         params: &E::Params
     ) -> edwards::Point<E, PrimeOrder>
     {
-        params.generator(FixedGenerators::ValueCommitmentValue)
-              .mul(self.value, params)
+        edwards::Point::fixed_base_mul_secret(FixedGenerators::ValueCommitmentValue, self.value, params)
               .add(
-                  &params.generator(FixedGenerators::ValueCommitmentRandomness)
-                  .mul(self.randomness, params),
+                  &edwards::Point::fixed_base_mul_secret(FixedGenerators::ValueCommitmentRandomness, self.randomness, params),
                   params
               )
     }
+
+    /// Reads the value and randomness, 40 bytes in total.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let value = reader.read_u64::<LittleEndian>()?;
+        let randomness = read_scalar::<E::Fs, _>(&mut reader)?;
+
+        Ok(ValueCommitment {
+            value: value,
+            randomness: randomness
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.value)?;
+        write_scalar::<E::Fs, _>(&self.randomness, &mut writer)
+    }
 }
 
-#[derive(Clone)]
 pub struct ProofGenerationKey<E: JubjubEngine> {
     pub ak: edwards::Point<E, PrimeOrder>,
     pub rsk: E::Fs
 }
 
+impl<E: JubjubEngine> Drop for ProofGenerationKey<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.rsk, E::Fs::zero());
+    }
+}
+
 impl<E: JubjubEngine> ProofGenerationKey<E> {
+    /// Copies the key, including `rsk`. Like `ValueCommitment`, this
+    /// is not `Clone`.
+    pub fn clone_secret(&self) -> Self {
+        ProofGenerationKey {
+            ak: self.ak.clone(),
+            rsk: self.rsk
+        }
+    }
+
     pub fn into_viewing_key(&self, params: &E::Params) -> ViewingKey<E> {
         ViewingKey {
             ak: self.ak.clone(),
-            rk: params.generator(FixedGenerators::ProofGenerationKey)
-                      .mul(self.rsk, params)
+            rk: edwards::Point::fixed_base_mul_secret(FixedGenerators::ProofGenerationKey, self.rsk, params)
         }
     }
+
+    /// Reads `ak` and `rsk`, 64 bytes in total.
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> Result<Self, Error> {
+        let ak = read_prime_order_point::<E, _>(&mut reader, params)?;
+        let rsk = read_scalar::<E::Fs, _>(&mut reader)?;
+
+        Ok(ProofGenerationKey {
+            ak: ak,
+            rsk: rsk
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.ak.write(&mut writer)?;
+        write_scalar::<E::Fs, _>(&self.rsk, &mut writer)
+    }
 }
 
+#[derive(Clone)]
 pub struct ViewingKey<E: JubjubEngine> {
     pub ak: edwards::Point<E, PrimeOrder>,
     pub rk: edwards::Point<E, PrimeOrder>
 }
 
 impl<E: JubjubEngine> ViewingKey<E> {
-    fn ivk(&self) -> E::Fs {
-        let mut preimage = [0; 64];
+    /// The incoming viewing key, used to derive payment addresses
+    /// and to trial-decrypt notes sent to them.
+    pub fn ivk(&self) -> IncomingViewingKey<E> {
+        let mut preimage = SecretBuffer::new();
 
-        self.ak.write(&mut preimage[0..32]).unwrap();
-        self.rk.write(&mut preimage[32..64]).unwrap();
+        self.ak.write(&mut preimage.0[0..32]).unwrap();
+        self.rk.write(&mut preimage.0[32..64]).unwrap();
 
+        // The BLAKE2s state also holds the preimage, and is not zeroized.
         let mut h = Blake2s::with_params(32, &[], &[], constants::CRH_IVK_PERSONALIZATION);
-        h.update(&preimage);
-        let mut h = h.finalize().as_ref().to_vec();
+        h.update(&preimage.0);
+        let h = h.finalize();
 
-        // Drop the first five bits, so it can be interpreted as a scalar.
-        h[0] &= 0b0000_0111;
+        IncomingViewingKey(drop_5_to_scalar::<E>(h.as_ref()))
+    }
 
-        let mut e = <E::Fs as PrimeField>::Repr::default();
-        e.read_be(&h[..]).unwrap();
+    pub fn into_payment_address(
+        &self,
+        diversifier: Diversifier,
+        params: &E::Params
+    ) -> Result<PaymentAddress<E>, Error>
+    {
+        self.ivk().into_payment_address(diversifier, params)
+    }
+
+    /// Reads `ak` and `rk`, 64 bytes in total.
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> Result<Self, Error> {
+        let ak = read_prime_order_point::<E, _>(&mut reader, params)?;
+        let rk = read_prime_order_point::<E, _>(&mut reader, params)?;
+
+        Ok(ViewingKey {
+            ak: ak,
+            rk: rk
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.ak.write(&mut writer)?;
+        self.rk.write(&mut writer)
+    }
+}
+
+/// The incoming viewing key, which is sufficient to derive payment
+/// addresses and to detect notes sent to them, but not to spend them
+/// or compute their nullifiers.
+pub struct IncomingViewingKey<E: JubjubEngine>(pub E::Fs);
+
+impl<E: JubjubEngine> Drop for IncomingViewingKey<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.0, E::Fs::zero());
+    }
+}
 
-        E::Fs::from_repr(e).expect("should be a valid scalar")
+impl<E: JubjubEngine> IncomingViewingKey<E> {
+    /// Copies the key. This is not `Clone`, as the key must not be
+    /// copied implicitly.
+    pub fn clone_secret(&self) -> Self {
+        IncomingViewingKey(self.0)
     }
 
     pub fn into_payment_address(
         &self,
         diversifier: Diversifier,
         params: &E::Params
-    ) -> Option<PaymentAddress<E>>
+    ) -> Result<PaymentAddress<E>, Error>
     {
         diversifier.g_d(params).map(|g_d| {
-            let pk_d = g_d.mul(self.ivk(), params);
+            let pk_d = g_d.mul_secret(self.0, params);
 
             PaymentAddress {
                 pk_d: pk_d,
@@ -106,6 +236,34 @@ impl<E: JubjubEngine> ViewingKey<E> {
             }
         })
     }
+
+    /// Reads the little endian encoding of an incoming viewing key,
+    /// which must be less than 2^251.
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        let ivk = read_scalar::<E::Fs, R>(reader)?;
+
+        if ivk.into_repr().num_bits() > 251 {
+            return Err(Error::NonCanonicalEncoding);
+        }
+
+        Ok(IncomingViewingKey(ivk))
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_scalar::<E::Fs, W>(&self.0, writer)
+    }
+}
+
+impl<'a, E: JubjubEngine> From<&'a ViewingKey<E>> for IncomingViewingKey<E> {
+    fn from(vk: &'a ViewingKey<E>) -> Self {
+        vk.ivk()
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for IncomingViewingKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -115,9 +273,10 @@ impl Diversifier {
     pub fn g_d<E: JubjubEngine>(
         &self,
         params: &E::Params
-    ) -> Option<edwards::Point<E, PrimeOrder>>
+    ) -> Result<edwards::Point<E, PrimeOrder>, Error>
     {
         group_hash::<E>(&self.0, constants::KEY_DIVERSIFICATION_PERSONALIZATION, params)
+            .map_err(|_| Error::InvalidDiversifier)
     }
 }
 
@@ -131,7 +290,7 @@ impl<E: JubjubEngine> PaymentAddress<E> {
     pub fn g_d(
         &self,
         params: &E::Params
-    ) -> Option<edwards::Point<E, PrimeOrder>>
+    ) -> Result<edwards::Point<E, PrimeOrder>, Error>
     {
         self.diversifier.g_d(params)
     }
@@ -141,7 +300,7 @@ impl<E: JubjubEngine> PaymentAddress<E> {
         value: u64,
         randomness: E::Fs,
         params: &E::Params
-    ) -> Option<Note<E>>
+    ) -> Result<Note<E>, Error>
     {
         self.g_d(params).map(|g_d| {
             Note {
@@ -152,6 +311,27 @@ impl<E: JubjubEngine> PaymentAddress<E> {
             }
         })
     }
+
+    /// Reads the diversifier and `pk_d`, 43 bytes in total. The
+    /// diversifier must be valid.
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> Result<Self, Error> {
+        let mut d = [0u8; 11];
+        reader.read_exact(&mut d)?;
+        let diversifier = Diversifier(d);
+        diversifier.g_d::<E>(params)?;
+
+        let pk_d = read_prime_order_point::<E, _>(&mut reader, params)?;
+
+        Ok(PaymentAddress {
+            pk_d: pk_d,
+            diversifier: diversifier
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.diversifier.0)?;
+        self.pk_d.write(&mut writer)
+    }
 }
 
 pub struct Note<E: JubjubEngine> {
@@ -165,6 +345,12 @@ pub struct Note<E: JubjubEngine> {
     pub r: E::Fs
 }
 
+impl<E: JubjubEngine> Drop for Note<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.r, E::Fs::zero());
+    }
+}
+
 impl<E: JubjubEngine> Note<E> {
     pub fn uncommitted() -> E::Fr {
         // The smallest u-coordinate that is not on the curve
@@ -203,8 +389,7 @@ impl<E: JubjubEngine> Note<E> {
         );
 
         // Compute final commitment
-        params.generator(FixedGenerators::NoteCommitmentRandomness)
-              .mul(self.r, params)
+        edwards::Point::fixed_base_mul_secret(FixedGenerators::NoteCommitmentRandomness, self.r, params)
               .add(&hash_of_contents, params)
     }
 
@@ -221,28 +406,24 @@ impl<E: JubjubEngine> Note<E> {
         let cm_plus_position = self
             .cm_full_point(params)
             .add(
-                &params.generator(FixedGenerators::NullifierPosition)
-                       .mul(position, params),
+                &edwards::Point::fixed_base_mul_secret(FixedGenerators::NullifierPosition, position, params),
                 params
             );
 
         // Compute nr = drop_5(BLAKE2s(rk | cm_plus_position))
-        let mut nr_preimage = [0u8; 64];
-        viewing_key.rk.write(&mut nr_preimage[0..32]).unwrap();
-        cm_plus_position.write(&mut nr_preimage[32..64]).unwrap();
+        let mut nr_preimage = SecretBuffer::new();
+        viewing_key.rk.write(&mut nr_preimage.0[0..32]).unwrap();
+        cm_plus_position.write(&mut nr_preimage.0[32..64]).unwrap();
+        // The BLAKE2s state also holds the preimage, and is not zeroized.
         let mut h = Blake2s::with_params(32, &[], &[], constants::PRF_NR_PERSONALIZATION);
-        h.update(&nr_preimage);
-        let mut h = h.finalize().as_ref().to_vec();
-
-        // Drop the first five bits, so it can be interpreted as a scalar.
-        h[0] &= 0b0000_0111;
+        h.update(&nr_preimage.0);
+        let h = h.finalize();
 
-        let mut e = <E::Fs as PrimeField>::Repr::default();
-        e.read_be(&h[..]).unwrap();
+        let mut nr = drop_5_to_scalar::<E>(h.as_ref());
+        let nf = viewing_key.ak.mul_secret(nr, params);
+        zeroize(&mut nr, E::Fs::zero());
 
-        let nr = E::Fs::from_repr(e).expect("should be a valid scalar");
-
-        viewing_key.ak.mul(nr, params)
+        nf
     }
 
     /// Computes the note commitment
@@ -252,4 +433,203 @@ impl<E: JubjubEngine> Note<E> {
         // commitment to the x-coordinate is an injective encoding.
         self.cm_full_point(params).into_xy().0
     }
+
+    /// Reads the value, `g_d`, `pk_d` and the commitment randomness,
+    /// 104 bytes in total.
+    pub fn read<R: Read>(mut reader: R, params: &E::Params) -> Result<Self, Error> {
+        let value = reader.read_u64::<LittleEndian>()?;
+        let g_d = read_prime_order_point::<E, _>(&mut reader, params)?;
+        let pk_d = read_prime_order_point::<E, _>(&mut reader, params)?;
+        let r = read_scalar::<E::Fs, _>(&mut reader)?;
+
+        Ok(Note {
+            value: value,
+            g_d: g_d,
+            pk_d: pk_d,
+            r: r
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.value)?;
+        self.g_d.write(&mut writer)?;
+        self.pk_d.write(&mut writer)?;
+        write_scalar::<E::Fs, _>(&self.r, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::{Bls12, Fr};
+    use pairing::Field;
+    use jubjub::{JubjubBls12, Unknown, edwards};
+    use super::*;
+
+    #[test]
+    fn test_incoming_viewing_key() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let viewing_key = ProofGenerationKey::<Bls12> {
+                ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+                rsk: rng.gen()
+            }.into_viewing_key(params);
+            let ivk = IncomingViewingKey::from(&viewing_key);
+            assert!(ivk == viewing_key.ivk());
+
+            let mut bytes = vec![];
+            ivk.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 32);
+            assert_eq!(bytes[31] & 0b1111_1000, 0);
+            assert!(IncomingViewingKey::<Bls12>::read(&bytes[..]).unwrap() == ivk);
+
+            // Addresses agree with the full viewing key.
+            for i in 0..10u8 {
+                let diversifier = Diversifier([i; 11]);
+                match (ivk.into_payment_address(diversifier, params),
+                       viewing_key.into_payment_address(diversifier, params))
+                {
+                    (Ok(a), Ok(b)) => assert!(a.pk_d == b.pk_d),
+                    (Err(_), Err(_)) => {},
+                    _ => panic!("addresses should agree")
+                }
+            }
+        }
+
+        // Scalars wider than 251 bits are rejected.
+        let mut bytes = [0u8; 32];
+        bytes[31] = 0b0000_1000;
+        assert!(IncomingViewingKey::<Bls12>::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_read_write() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let value_commitment = ValueCommitment::<Bls12> {
+                value: rng.gen(),
+                randomness: rng.gen()
+            };
+            let mut bytes = vec![];
+            value_commitment.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 40);
+            let value_commitment_2 = ValueCommitment::<Bls12>::read(&bytes[..]).unwrap();
+            assert_eq!(value_commitment_2.value, value_commitment.value);
+            assert_eq!(value_commitment_2.randomness, value_commitment.randomness);
+
+            let proof_generation_key = ProofGenerationKey::<Bls12> {
+                ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+                rsk: rng.gen()
+            };
+            let mut bytes = vec![];
+            proof_generation_key.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 64);
+            let proof_generation_key_2 = ProofGenerationKey::<Bls12>::read(&bytes[..], params).unwrap();
+            assert!(proof_generation_key_2.ak == proof_generation_key.ak);
+            assert_eq!(proof_generation_key_2.rsk, proof_generation_key.rsk);
+
+            let viewing_key = proof_generation_key.into_viewing_key(params);
+            let mut bytes = vec![];
+            viewing_key.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 64);
+            let viewing_key_2 = ViewingKey::<Bls12>::read(&bytes[..], params).unwrap();
+            assert!(viewing_key_2.ak == viewing_key.ak);
+            assert!(viewing_key_2.rk == viewing_key.rk);
+
+            let payment_address = loop {
+                if let Ok(a) = viewing_key.into_payment_address(Diversifier(rng.gen()), params) {
+                    break a;
+                }
+            };
+            let mut bytes = vec![];
+            payment_address.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 43);
+            let payment_address_2 = PaymentAddress::<Bls12>::read(&bytes[..], params).unwrap();
+            assert!(payment_address_2.pk_d == payment_address.pk_d);
+            assert_eq!(payment_address_2.diversifier.0, payment_address.diversifier.0);
+
+            let note = payment_address.create_note(rng.gen(), rng.gen(), params).unwrap();
+            let mut bytes = vec![];
+            note.write(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), 104);
+            let note_2 = Note::<Bls12>::read(&bytes[..], params).unwrap();
+            assert_eq!(note_2.cm(params), note.cm(params));
+            assert_eq!(note_2.value, note.value);
+        }
+    }
+
+    #[test]
+    fn test_read_rejects_invalid_encodings() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let viewing_key = ProofGenerationKey::<Bls12> {
+            ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+            rsk: rng.gen()
+        }.into_viewing_key(params);
+        let mut bytes = vec![];
+        viewing_key.write(&mut bytes).unwrap();
+
+        // The point (0, -1) has order 2.
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let p = edwards::Point::<Bls12, Unknown>::get_for_y(minus_one, false, params).unwrap();
+        let mut bytes_2 = bytes.clone();
+        p.write(&mut bytes_2[32..64]).unwrap();
+        match ViewingKey::<Bls12>::read(&bytes_2[..], params) {
+            Err(Error::SmallOrderPoint) => {},
+            _ => panic!("small order points should be rejected")
+        }
+
+        // The identity with its sign bit set is a non-canonical encoding.
+        let mut bytes_2 = bytes.clone();
+        for b in &mut bytes_2[32..64] {
+            *b = 0;
+        }
+        bytes_2[32] = 1;
+        assert!(ViewingKey::<Bls12>::read(&bytes_2[..], params).is_ok());
+        bytes_2[63] |= 0x80;
+        match ViewingKey::<Bls12>::read(&bytes_2[..], params) {
+            Err(Error::NonCanonicalEncoding) => {},
+            _ => panic!("non-canonical encodings should be rejected")
+        }
+
+        // Scalars must be canonical.
+        let mut bytes = vec![];
+        viewing_key.ak.write(&mut bytes).unwrap();
+        bytes.extend_from_slice(&[0; 32]);
+        assert!(ProofGenerationKey::<Bls12>::read(&bytes[..], params).is_ok());
+        for b in &mut bytes[32..] {
+            *b = 0xff;
+        }
+        match ProofGenerationKey::<Bls12>::read(&bytes[..], params) {
+            Err(Error::NonCanonicalEncoding) => {},
+            _ => panic!("non-canonical scalars should be rejected")
+        }
+
+        // Diversifiers must be valid.
+        let d = loop {
+            let d: [u8; 11] = rng.gen();
+            if Diversifier(d).g_d::<Bls12>(params).is_err() {
+                break d;
+            }
+        };
+        let mut bytes = d.to_vec();
+        viewing_key.ak.write(&mut bytes).unwrap();
+        match PaymentAddress::<Bls12>::read(&bytes[..], params) {
+            Err(Error::InvalidDiversifier) => {},
+            _ => panic!("invalid diversifiers should be rejected")
+        }
+        match viewing_key.into_payment_address(Diversifier(d), params) {
+            Err(Error::InvalidDiversifier) => {},
+            _ => panic!("invalid diversifiers should be rejected")
+        }
+
+        // Truncated encodings are rejected.
+        assert!(Note::<Bls12>::read(&[0u8; 40][..], params).is_err());
+    }
 }
@@ -0,0 +1,198 @@
+//! Creation of Groth16 proofs for the `Spend` and `Output` circuits
+//! from native witnesses.
+//!
+//! Each proof is returned together with the public inputs it was
+//! created for, so that the caller can publish them alongside it.
+
+use bellman::SynthesisError;
+
+use bellman::groth16::{
+    create_random_proof,
+    generate_random_parameters,
+    Parameters,
+    Proof
+};
+
+use rand::Rng;
+
+use jubjub::JubjubEngine;
+
+use circuit::sapling::{
+    Output,
+    Spend
+};
+
+use merkle_tree::{
+    CommitmentTreePath,
+    SAPLING_COMMITMENT_TREE_DEPTH
+};
+
+use primitives::{
+    Diversifier,
+    Note,
+    PaymentAddress,
+    ProofGenerationKey,
+    ValueCommitment
+};
+
+use public_inputs::{
+    OutputPublicInputs,
+    SpendPublicInputs
+};
+
+/// Generates random parameters for the `Spend` circuit over a
+/// commitment tree of depth `SAPLING_COMMITMENT_TREE_DEPTH`. These
+/// are only suitable for testing, as whoever runs this learns the
+/// trapdoor.
+pub fn spend_parameters<E: JubjubEngine, R: Rng>(
+    params: &E::Params,
+    rng: &mut R
+) -> Result<Parameters<E>, SynthesisError>
+{
+    generate_random_parameters::<E, _, _>(
+        Spend {
+            params: params,
+            value_commitment: None,
+            proof_generation_key: None,
+            payment_address: None,
+            commitment_randomness: None,
+            auth_path: vec![None; SAPLING_COMMITMENT_TREE_DEPTH]
+        },
+        rng
+    )
+}
+
+/// Generates random parameters for the `Output` circuit. These are
+/// only suitable for testing, as whoever runs this learns the
+/// trapdoor.
+pub fn output_parameters<E: JubjubEngine, R: Rng>(
+    params: &E::Params,
+    rng: &mut R
+) -> Result<Parameters<E>, SynthesisError>
+{
+    generate_random_parameters::<E, _, _>(
+        Output {
+            params: params,
+            value_commitment: None,
+            payment_address: None,
+            commitment_randomness: None,
+            esk: None
+        },
+        rng
+    )
+}
+
+/// Reconstructs the address a note was sent to, checking that the
+/// diversifier matches the note.
+fn payment_address<E: JubjubEngine>(
+    note: &Note<E>,
+    diversifier: Diversifier,
+    params: &E::Params
+) -> Result<PaymentAddress<E>, SynthesisError>
+{
+    match diversifier.g_d::<E>(params) {
+        Ok(ref g_d) if *g_d == note.g_d => {
+            Ok(PaymentAddress {
+                pk_d: note.pk_d.clone(),
+                diversifier: diversifier
+            })
+        },
+        _ => Err(SynthesisError::Unsatisfiable)
+    }
+}
+
+/// Creates a proof that `note`, sent to the address with the given
+/// diversifier, is at the position of `path` in the tree and can be
+/// spent with `proof_generation_key`. The value is committed to with
+/// randomness `rcv`.
+///
+/// Returns the proof with the value commitment, anchor and nullifier
+/// as public inputs.
+pub fn create_spend_proof<E: JubjubEngine, R: Rng>(
+    note: &Note<E>,
+    diversifier: Diversifier,
+    proof_generation_key: &ProofGenerationKey<E>,
+    path: &CommitmentTreePath<E>,
+    rcv: E::Fs,
+    proving_key: &Parameters<E>,
+    params: &E::Params,
+    rng: &mut R
+) -> Result<(Proof<E>, SpendPublicInputs<E>), SynthesisError>
+{
+    let payment_address = payment_address(note, diversifier, params)?;
+
+    let value_commitment = ValueCommitment {
+        value: note.value,
+        randomness: rcv
+    };
+
+    let viewing_key = proof_generation_key.into_viewing_key(params);
+
+    let public_inputs = SpendPublicInputs::from_note(
+        &value_commitment,
+        note,
+        &viewing_key,
+        path,
+        params
+    );
+
+    let proof = create_random_proof(
+        Spend {
+            params: params,
+            value_commitment: Some(value_commitment),
+            proof_generation_key: Some(proof_generation_key.clone_secret()),
+            payment_address: Some(payment_address),
+            commitment_randomness: Some(note.r),
+            auth_path: path.auth_path.clone()
+        },
+        proving_key,
+        rng
+    )?;
+
+    Ok((proof, public_inputs))
+}
+
+/// Creates a proof that `note` is correctly committed to and that
+/// `epk = [esk] g_d` for the address with the given diversifier.
+/// The value is committed to with randomness `rcv`.
+///
+/// Returns the proof with the value commitment, `epk` and note
+/// commitment as public inputs.
+pub fn create_output_proof<E: JubjubEngine, R: Rng>(
+    note: &Note<E>,
+    diversifier: Diversifier,
+    esk: E::Fs,
+    rcv: E::Fs,
+    proving_key: &Parameters<E>,
+    params: &E::Params,
+    rng: &mut R
+) -> Result<(Proof<E>, OutputPublicInputs<E>), SynthesisError>
+{
+    let payment_address = payment_address(note, diversifier, params)?;
+
+    let value_commitment = ValueCommitment {
+        value: note.value,
+        randomness: rcv
+    };
+
+    let public_inputs = OutputPublicInputs::from_note(
+        &value_commitment,
+        note,
+        esk,
+        params
+    );
+
+    let proof = create_random_proof(
+        Output {
+            params: params,
+            value_commitment: Some(value_commitment),
+            payment_address: Some(payment_address),
+            commitment_randomness: Some(note.r),
+            esk: Some(esk)
+        },
+        proving_key,
+        rng
+    )?;
+
+    Ok((proof, public_inputs))
+}
@@ -0,0 +1,262 @@
+//! The public inputs of the `Spend` and `Output` circuits, computed
+//! from native values in the order the circuits expose them.
+//!
+//! Points are exposed by `EdwardsPoint::inputize` as their x and then
+//! y coordinates, while the anchor and note commitment are single
+//! field elements.
+
+use pairing::{
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use std::io::{
+    self,
+    Write
+};
+
+use jubjub::{
+    edwards,
+    JubjubEngine,
+    Unknown
+};
+
+use merkle_tree::CommitmentTreePath;
+
+use primitives::{
+    Note,
+    ValueCommitment,
+    ViewingKey
+};
+
+fn write_field_elements<E: JubjubEngine, W: Write>(
+    elements: &[E::Fr],
+    mut writer: W
+) -> io::Result<()>
+{
+    for e in elements {
+        let mut bytes = [0u8; 32];
+        e.into_repr().write_be(&mut bytes[..])?;
+        bytes.reverse();
+        writer.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// The public inputs of the `Spend` circuit.
+pub struct SpendPublicInputs<E: JubjubEngine> {
+    /// The value commitment
+    pub cv: edwards::Point<E, Unknown>,
+    /// The root of the note commitment tree
+    pub anchor: E::Fr,
+    /// The nullifier of the spent note
+    pub nf: edwards::Point<E, Unknown>
+}
+
+impl<E: JubjubEngine> SpendPublicInputs<E> {
+    pub fn new(
+        cv: edwards::Point<E, Unknown>,
+        anchor: E::Fr,
+        nf: edwards::Point<E, Unknown>
+    ) -> Self
+    {
+        SpendPublicInputs {
+            cv: cv,
+            anchor: anchor,
+            nf: nf
+        }
+    }
+
+    /// Computes the public inputs for spending `note`, authenticated
+    /// by `path`, with the given value commitment and viewing key.
+    pub fn from_note(
+        value_commitment: &ValueCommitment<E>,
+        note: &Note<E>,
+        viewing_key: &ViewingKey<E>,
+        path: &CommitmentTreePath<E>,
+        params: &E::Params
+    ) -> Self
+    {
+        SpendPublicInputs {
+            cv: value_commitment.cm(params).into(),
+            anchor: path.root(note.cm(params), params),
+            nf: note.nf(viewing_key, path.position, params).into()
+        }
+    }
+
+    /// The field elements in the order the circuit exposes them:
+    /// the value commitment, the anchor and the nullifier.
+    pub fn to_field_elements(&self) -> Vec<E::Fr> {
+        let cv = self.cv.into_xy();
+        let nf = self.nf.into_xy();
+
+        vec![cv.0, cv.1, self.anchor, nf.0, nf.1]
+    }
+
+    /// Writes the field elements in order, each as 32 little
+    /// endian bytes.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_field_elements::<E, W>(&self.to_field_elements(), writer)
+    }
+}
+
+/// The public inputs of the `Output` circuit.
+pub struct OutputPublicInputs<E: JubjubEngine> {
+    /// The value commitment
+    pub cv: edwards::Point<E, Unknown>,
+    /// The ephemeral public key
+    pub epk: edwards::Point<E, Unknown>,
+    /// The note commitment
+    pub cm: E::Fr
+}
+
+impl<E: JubjubEngine> OutputPublicInputs<E> {
+    pub fn new(
+        cv: edwards::Point<E, Unknown>,
+        epk: edwards::Point<E, Unknown>,
+        cm: E::Fr
+    ) -> Self
+    {
+        OutputPublicInputs {
+            cv: cv,
+            epk: epk,
+            cm: cm
+        }
+    }
+
+    /// Computes the public inputs for creating `note` with the given
+    /// value commitment and ephemeral secret key.
+    pub fn from_note(
+        value_commitment: &ValueCommitment<E>,
+        note: &Note<E>,
+        esk: E::Fs,
+        params: &E::Params
+    ) -> Self
+    {
+        OutputPublicInputs {
+            cv: value_commitment.cm(params).into(),
+            epk: note.g_d.mul(esk, params).into(),
+            cm: note.cm(params)
+        }
+    }
+
+    /// The field elements in the order the circuit exposes them:
+    /// the value commitment, the ephemeral public key and the
+    /// note commitment.
+    pub fn to_field_elements(&self) -> Vec<E::Fr> {
+        let cv = self.cv.into_xy();
+        let epk = self.epk.into_xy();
+
+        vec![cv.0, cv.1, epk.0, epk.1, self.cm]
+    }
+
+    /// Writes the field elements in order, each as 32 little
+    /// endian bytes.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_field_elements::<E, W>(&self.to_field_elements(), writer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::Field;
+    use pairing::bls12_381::{Bls12, Fr};
+    use bellman::Circuit;
+    use circuit::test::TestConstraintSystem;
+    use circuit::sapling::{Output, Spend};
+    use jubjub::{JubjubBls12, fs};
+    use keys::{ExpandedSpendingKey, FullViewingKey};
+    use merkle_tree::{CommitmentTree, IncrementalWitness};
+    use primitives::PaymentAddress;
+    use super::*;
+
+    #[test]
+    fn test_spend_public_inputs() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[5u8; 32]);
+        let proof_generation_key = expsk.proof_generation_key(params);
+        let viewing_key = proof_generation_key.into_viewing_key(params);
+        let (_, to) = FullViewingKey::from_expanded_spending_key(&expsk, params)
+                                     .default_address(params).unwrap();
+        let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        for _ in 0..3 {
+            tree.append(rng.gen(), params).unwrap();
+        }
+        tree.append(note.cm(params), params).unwrap();
+        let mut witness = IncrementalWitness::from_tree(&tree);
+        witness.append(rng.gen(), params).unwrap();
+        let path = witness.path(params).unwrap();
+
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: rng.gen()
+        };
+
+        let inputs = SpendPublicInputs::from_note(&value_commitment, &note, &viewing_key, &path, params);
+        assert!(inputs.anchor == witness.root(params));
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        Spend {
+            params: params,
+            value_commitment: Some(value_commitment.clone_secret()),
+            proof_generation_key: Some(proof_generation_key.clone_secret()),
+            payment_address: Some(to.clone()),
+            commitment_randomness: Some(note.r),
+            auth_path: path.auth_path.clone()
+        }.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs.to_field_elements()));
+
+        let mut bytes = vec![];
+        inputs.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 5 * 32);
+
+        // A different anchor does not match.
+        let mut anchor = inputs.anchor;
+        anchor.add_assign(&Fr::one());
+        let other = SpendPublicInputs::new(inputs.cv.clone(), anchor, inputs.nf.clone());
+        assert!(!cs.verify(&other.to_field_elements()));
+    }
+
+    #[test]
+    fn test_output_public_inputs() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[6u8; 32]);
+        let (_, to): (_, PaymentAddress<Bls12>) = FullViewingKey::from_expanded_spending_key(&expsk, params)
+                                                                .default_address(params).unwrap();
+        let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+        let esk: fs::Fs = rng.gen();
+
+        let value_commitment = ValueCommitment {
+            value: note.value,
+            randomness: rng.gen()
+        };
+
+        let inputs = OutputPublicInputs::from_note(&value_commitment, &note, esk, params);
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+        Output {
+            params: params,
+            value_commitment: Some(value_commitment.clone_secret()),
+            payment_address: Some(to.clone()),
+            commitment_randomness: Some(note.r),
+            esk: Some(esk)
+        }.synthesize(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs.to_field_elements()));
+
+        // Swapping cv and epk does not match.
+        let other = OutputPublicInputs::new(inputs.epk.clone(), inputs.cv.clone(), inputs.cm);
+        assert!(!cs.verify(&other.to_field_elements()));
+    }
+}
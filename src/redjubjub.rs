@@ -0,0 +1,301 @@
+//! Implementation of RedJubjub, a specialization of RedDSA to the Jubjub
+//! curve. These are Schnorr signatures whose keys can be re-randomized,
+//! used to authorize spends with keys consistent with the `Spend` circuit.
+//!
+//! Signing and verification take the base the keys are defined over.
+//! For spend authorization this is `FixedGenerators::ProofGenerationKey`,
+//! so that the public key of `PrivateKey(rsk)` is `ViewingKey::rk`.
+
+use pairing::{
+    Field
+};
+
+use rand::Rng;
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use constants;
+
+use jubjub::{
+    FixedGenerators,
+    JubjubEngine,
+    Unknown,
+    edwards::Point
+};
+
+use util::{
+    hash_to_scalar,
+    read_scalar,
+    write_scalar,
+    zeroize
+};
+
+fn h_star<E: JubjubEngine>(a: &[u8], b: &[u8]) -> E::Fs {
+    hash_to_scalar::<E>(constants::REDJUBJUB_H_PERSONALIZATION, a, b)
+}
+
+/// A RedJubjub signature, consisting of the encoding of the
+/// commitment point `R` and the response scalar `S`.
+#[derive(Copy, Clone)]
+pub struct Signature {
+    rbar: [u8; 32],
+    sbar: [u8; 32]
+}
+
+/// A RedJubjub signing key.
+pub struct PrivateKey<E: JubjubEngine>(pub E::Fs);
+
+impl<E: JubjubEngine> Drop for PrivateKey<E> {
+    fn drop(&mut self) {
+        zeroize(&mut self.0, E::Fs::zero());
+    }
+}
+
+/// A RedJubjub verification key.
+pub struct PublicKey<E: JubjubEngine>(pub Point<E, Unknown>);
+
+impl Signature {
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut rbar = [0u8; 32];
+        let mut sbar = [0u8; 32];
+        reader.read_exact(&mut rbar)?;
+        reader.read_exact(&mut sbar)?;
+
+        Ok(Signature {
+            rbar: rbar,
+            sbar: sbar
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.rbar)?;
+        writer.write_all(&self.sbar)
+    }
+}
+
+impl<E: JubjubEngine> PrivateKey<E> {
+    /// Randomizes the key by adding `alpha`, so that signatures
+    /// cannot be linked to the original key.
+    pub fn randomize(&self, alpha: E::Fs) -> Self {
+        let mut tmp = self.0;
+        tmp.add_assign(&alpha);
+
+        PrivateKey(tmp)
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        Ok(PrivateKey(read_scalar::<E::Fs, R>(reader)?))
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_scalar::<E::Fs, W>(&self.0, writer)
+    }
+
+    pub fn sign<R: Rng>(
+        &self,
+        msg: &[u8],
+        rng: &mut R,
+        p_g: FixedGenerators,
+        params: &E::Params
+    ) -> Signature
+    {
+        // T = (l_H + 128) bits of randomness
+        // For H*, l_H = 512 bits
+        let mut t = [0u8; 80];
+        rng.fill_bytes(&mut t[..]);
+
+        // r = H*(T || M)
+        let r = h_star::<E>(&t[..], msg);
+
+        // R = r . P_G
+        let r_g = Point::fixed_base_mul_secret(p_g, r, params);
+        let mut rbar = [0u8; 32];
+        r_g.write(&mut rbar[..]).expect("Jubjub points should serialize to 32 bytes");
+
+        // S = r + H*(Rbar || M) . sk
+        let mut s = h_star::<E>(&rbar[..], msg);
+        s.mul_assign(&self.0);
+        s.add_assign(&r);
+        let mut sbar = [0u8; 32];
+        write_scalar::<E::Fs, &mut [u8]>(&s, &mut sbar[..])
+            .expect("Jubjub scalars should serialize to 32 bytes");
+
+        Signature {
+            rbar: rbar,
+            sbar: sbar
+        }
+    }
+}
+
+impl<E: JubjubEngine> PublicKey<E> {
+    pub fn from_private(
+        privkey: &PrivateKey<E>,
+        p_g: FixedGenerators,
+        params: &E::Params
+    ) -> Self
+    {
+        PublicKey(Point::fixed_base_mul_secret(p_g, privkey.0, params).into())
+    }
+
+    /// Randomizes the key by adding `[alpha] P_G`, matching
+    /// `PrivateKey::randomize`.
+    pub fn randomize(
+        &self,
+        alpha: E::Fs,
+        p_g: FixedGenerators,
+        params: &E::Params
+    ) -> Self
+    {
+        let r_g: Point<E, Unknown> = Point::fixed_base_mul_secret(p_g, alpha, params).into();
+
+        PublicKey(r_g.add(&self.0, params))
+    }
+
+    pub fn read<R: Read>(reader: R, params: &E::Params) -> io::Result<Self> {
+        Ok(PublicKey(Point::read(reader, params)?))
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.0.write(writer)
+    }
+
+    pub fn verify(
+        &self,
+        msg: &[u8],
+        sig: &Signature,
+        p_g: FixedGenerators,
+        params: &E::Params
+    ) -> bool
+    {
+        // c = H*(Rbar || M)
+        let c = h_star::<E>(&sig.rbar[..], msg);
+
+        // Signature checks:
+        // R != invalid
+        let r = match Point::read(&sig.rbar[..], params) {
+            Ok(r) => r,
+            Err(_) => return false
+        };
+        // S < order(G)
+        // (E::Fs guarantees its representation is in the field)
+        let s = match read_scalar::<E::Fs, &[u8]>(&sig.sbar[..]) {
+            Ok(s) => s,
+            Err(_) => return false
+        };
+
+        // 0 = h_G(-S . P_G + R + c . vk)
+        let s_g: Point<E, Unknown> = Point::fixed_base_mul(p_g, s, params).negate().into();
+
+        self.0.mul_wnaf(c, params)
+              .add(&r, params)
+              .add(&s_g, params)
+              .mul_by_cofactor(params) == Point::zero()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::Bls12;
+    use jubjub::{JubjubBls12, FixedGenerators, Unknown, edwards};
+    use primitives::ProofGenerationKey;
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::ProofGenerationKey;
+
+        for _ in 0..100 {
+            let sk = PrivateKey::<Bls12>(rng.gen());
+            let vk = PublicKey::from_private(&sk, p_g, params);
+
+            let msg1 = b"Foo bar";
+            let msg2 = b"Spam eggs";
+
+            let sig1 = sk.sign(msg1, rng, p_g, params);
+            let sig2 = sk.sign(msg2, rng, p_g, params);
+
+            assert!(vk.verify(msg1, &sig1, p_g, params));
+            assert!(vk.verify(msg2, &sig2, p_g, params));
+            assert!(!vk.verify(msg1, &sig2, p_g, params));
+            assert!(!vk.verify(msg2, &sig1, p_g, params));
+
+            // Signatures are bound to the base.
+            assert!(!vk.verify(msg1, &sig1, FixedGenerators::SpendingKeyGenerator, params));
+
+            let alpha = rng.gen();
+            let rsk = sk.randomize(alpha);
+            let rvk = vk.randomize(alpha, p_g, params);
+
+            let sig1 = rsk.sign(msg1, rng, p_g, params);
+            let sig2 = rsk.sign(msg2, rng, p_g, params);
+
+            assert!(rvk.verify(msg1, &sig1, p_g, params));
+            assert!(rvk.verify(msg2, &sig2, p_g, params));
+            assert!(!rvk.verify(msg1, &sig2, p_g, params));
+            assert!(!vk.verify(msg1, &sig1, p_g, params));
+        }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::ProofGenerationKey;
+
+        for _ in 0..100 {
+            let sk = PrivateKey::<Bls12>(rng.gen());
+            let vk = PublicKey::from_private(&sk, p_g, params);
+            let msg = b"Foo bar";
+            let sig = sk.sign(msg, rng, p_g, params);
+
+            let mut sk_bytes = [0u8; 32];
+            let mut vk_bytes = [0u8; 32];
+            let mut sig_bytes = [0u8; 64];
+            sk.write(&mut sk_bytes[..]).unwrap();
+            vk.write(&mut vk_bytes[..]).unwrap();
+            sig.write(&mut sig_bytes[..]).unwrap();
+
+            let sk_2 = PrivateKey::<Bls12>::read(&sk_bytes[..]).unwrap();
+            let vk_2 = PublicKey::from_private(&sk_2, p_g, params);
+            let mut vk_2_bytes = [0u8; 32];
+            vk_2.write(&mut vk_2_bytes[..]).unwrap();
+            assert!(vk_bytes == vk_2_bytes);
+
+            let vk_2 = PublicKey::<Bls12>::read(&vk_bytes[..], params).unwrap();
+            let sig_2 = Signature::read(&sig_bytes[..]).unwrap();
+            assert!(vk.verify(msg, &sig_2, p_g, params));
+            assert!(vk_2.verify(msg, &sig, p_g, params));
+            assert!(vk_2.verify(msg, &sig_2, p_g, params));
+        }
+    }
+
+    #[test]
+    fn test_consistent_with_proof_generation_key() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let p_g = FixedGenerators::ProofGenerationKey;
+
+        let proof_generation_key = ProofGenerationKey::<Bls12> {
+            ak: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+            rsk: rng.gen()
+        };
+        let viewing_key = proof_generation_key.into_viewing_key(params);
+
+        let sk = PrivateKey::<Bls12>(proof_generation_key.rsk);
+        let vk = PublicKey::from_private(&sk, p_g, params);
+
+        let rk: edwards::Point<Bls12, Unknown> = viewing_key.rk.clone().into();
+        assert!(vk.0 == rk);
+
+        let msg = b"Spend authorization";
+        let sig = sk.sign(msg, rng, p_g, params);
+        assert!(PublicKey(rk).verify(msg, &sig, p_g, params));
+    }
+}
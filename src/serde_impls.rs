@@ -0,0 +1,328 @@
+//! `Serialize` and `Deserialize` implementations, enabled by the
+//! `serde` feature. Values are serialized with their canonical byte
+//! encodings, as hex strings in human-readable formats and as byte
+//! strings otherwise.
+//!
+//! Deserialization needs the Jubjub parameters to decompress and
+//! validate points, so it is only implemented over `Bls12`, using a
+//! `JubjubBls12` instance that is constructed on first use. Points
+//! must be in the prime order subgroup, and encodings must be
+//! canonical and of exactly the right length.
+
+use pairing::bls12_381::Bls12;
+
+use serde::de::{
+    self,
+    Deserialize,
+    Deserializer,
+    SeqAccess,
+    Unexpected,
+    Visitor
+};
+
+use serde::ser::{
+    Serialize,
+    Serializer
+};
+
+use std::fmt;
+use std::sync::{Once, ONCE_INIT};
+
+use error::Error;
+
+use jubjub::{
+    edwards,
+    JubjubBls12,
+    JubjubEngine,
+    PrimeOrder
+};
+
+use jubjub::fs::Fs;
+
+use primitives::{
+    Note,
+    PaymentAddress,
+    ValueCommitment
+};
+
+use util::{
+    read_prime_order_point,
+    read_scalar,
+    write_scalar
+};
+
+/// Returns the parameters used to deserialize points, constructing
+/// them on first use.
+fn params() -> &'static JubjubBls12 {
+    static INIT: Once = ONCE_INIT;
+    static mut PARAMS: *const JubjubBls12 = 0 as *const JubjubBls12;
+
+    unsafe {
+        INIT.call_once(|| {
+            PARAMS = Box::into_raw(Box::new(JubjubBls12::new()));
+        });
+
+        &*PARAMS
+    }
+}
+
+const HEX_DIGITS: &'static [u8; 16] = b"0123456789abcdef";
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        s.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+
+    s
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    fn digit(c: u8) -> Option<u8> {
+        match c {
+            b'0'...b'9' => Some(c - b'0'),
+            b'a'...b'f' => Some(c - b'a' + 10),
+            b'A'...b'F' => Some(c - b'A' + 10),
+            _ => None
+        }
+    }
+
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+
+    s.chunks(2).map(|pair| {
+        match (digit(pair[0]), digit(pair[1])) {
+            (Some(hi), Some(lo)) => Some((hi << 4) | lo),
+            _ => None
+        }
+    }).collect()
+}
+
+fn serialize_encoding<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&to_hex(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+struct EncodingVisitor;
+
+impl<'de> Visitor<'de> for EncodingVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a hex string or a byte string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<u8>, E> {
+        from_hex(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = vec![];
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Deserializes an encoding and decodes it with `decode`, which must
+/// consume all of it.
+fn deserialize_encoding<'de, D, T, F>(deserializer: D, decode: F) -> Result<T, D::Error>
+    where D: Deserializer<'de>,
+          F: FnOnce(&mut &[u8]) -> Result<T, Error>
+{
+    let bytes = if deserializer.is_human_readable() {
+        deserializer.deserialize_str(EncodingVisitor)?
+    } else {
+        deserializer.deserialize_bytes(EncodingVisitor)?
+    };
+
+    let mut reader = &bytes[..];
+    let value = decode(&mut reader).map_err(<D::Error as de::Error>::custom)?;
+    if !reader.is_empty() {
+        return Err(de::Error::invalid_length(bytes.len(), &"a canonical encoding"));
+    }
+
+    Ok(value)
+}
+
+impl<E: JubjubEngine> Serialize for edwards::Point<E, PrimeOrder> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("writing to a Vec cannot fail");
+        serialize_encoding(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for edwards::Point<Bls12, PrimeOrder> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_encoding(deserializer, |reader| {
+            read_prime_order_point::<Bls12, _>(reader, params())
+        })
+    }
+}
+
+impl Serialize for Fs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = vec![];
+        write_scalar::<Fs, _>(self, &mut bytes).expect("writing to a Vec cannot fail");
+        serialize_encoding(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Fs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_encoding(deserializer, |reader| read_scalar::<Fs, _>(reader))
+    }
+}
+
+impl<E: JubjubEngine> Serialize for Note<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("writing to a Vec cannot fail");
+        serialize_encoding(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Note<Bls12> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_encoding(deserializer, |reader| Note::read(reader, params()))
+    }
+}
+
+impl<E: JubjubEngine> Serialize for PaymentAddress<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("writing to a Vec cannot fail");
+        serialize_encoding(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PaymentAddress<Bls12> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_encoding(deserializer, |reader| PaymentAddress::read(reader, params()))
+    }
+}
+
+impl<E: JubjubEngine> Serialize for ValueCommitment<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = vec![];
+        self.write(&mut bytes).expect("writing to a Vec cannot fail");
+        serialize_encoding(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ValueCommitment<Bls12> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_encoding(deserializer, |reader| ValueCommitment::read(reader))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::bls12_381::Bls12;
+    use serde_json;
+    use jubjub::{edwards, Unknown};
+    use jubjub::fs::Fs;
+    use primitives::{Diversifier, Note, PaymentAddress, ValueCommitment};
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+        assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+        assert_eq!(to_hex(&[0x01, 0xab, 0xff]), "01abff");
+        assert_eq!(from_hex("01ABff").unwrap(), vec![0x01, 0xab, 0xff]);
+        assert!(from_hex("0").is_none());
+        assert!(from_hex("0g").is_none());
+    }
+
+    #[test]
+    fn test_points_and_scalars() {
+        let params = params();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..10 {
+            let p = edwards::Point::<Bls12, Unknown>::rand(rng, params).mul_by_cofactor(params);
+            let mut bytes = vec![];
+            p.write(&mut bytes).unwrap();
+
+            let json = serde_json::to_string(&p).unwrap();
+            assert_eq!(json, format!("\"{}\"", to_hex(&bytes)));
+            assert!(serde_json::from_str::<edwards::Point<Bls12, PrimeOrder>>(&json).unwrap() == p);
+
+            let s: Fs = rng.gen();
+            let json = serde_json::to_string(&s).unwrap();
+            assert_eq!(serde_json::from_str::<Fs>(&json).unwrap(), s);
+        }
+
+        // Points outside of the prime order subgroup are rejected.
+        loop {
+            let p = edwards::Point::<Bls12, Unknown>::rand(rng, params);
+            if p.as_prime_order(params).is_none() {
+                let mut bytes = vec![];
+                p.write(&mut bytes).unwrap();
+                let json = format!("\"{}\"", to_hex(&bytes));
+                assert!(serde_json::from_str::<edwards::Point<Bls12, PrimeOrder>>(&json).is_err());
+                break;
+            }
+        }
+
+        // Non-canonical scalars and trailing bytes are rejected.
+        let json = format!("\"{}\"", to_hex(&[0xff; 32]));
+        assert!(serde_json::from_str::<Fs>(&json).is_err());
+        let json = format!("\"{}\"", to_hex(&[0x00; 33]));
+        assert!(serde_json::from_str::<Fs>(&json).is_err());
+    }
+
+    #[test]
+    fn test_primitives() {
+        let params = params();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let diversifier = loop {
+            let d = Diversifier(rng.gen());
+            if d.g_d::<Bls12>(params).is_ok() {
+                break d;
+            }
+        };
+        let address = PaymentAddress::<Bls12> {
+            pk_d: edwards::Point::rand(rng, params).mul_by_cofactor(params),
+            diversifier: diversifier
+        };
+
+        let json = serde_json::to_string(&address).unwrap();
+        let address_2: PaymentAddress<Bls12> = serde_json::from_str(&json).unwrap();
+        assert!(address_2.pk_d == address.pk_d);
+        assert_eq!(address_2.diversifier.0, address.diversifier.0);
+
+        let note = address.create_note(rng.gen(), rng.gen(), params).unwrap();
+        let json = serde_json::to_string(&note).unwrap();
+        let note_2: Note<Bls12> = serde_json::from_str(&json).unwrap();
+        assert_eq!(note_2.cm(params), note.cm(params));
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: rng.gen(),
+            randomness: rng.gen()
+        };
+        let json = serde_json::to_string(&value_commitment).unwrap();
+        let value_commitment_2: ValueCommitment<Bls12> = serde_json::from_str(&json).unwrap();
+        assert!(value_commitment_2.cm(params) == value_commitment.cm(params));
+    }
+}
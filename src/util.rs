@@ -0,0 +1,198 @@
+use pairing::{
+    Field,
+    PrimeField,
+    PrimeFieldRepr
+};
+
+use blake2_rfc::blake2b::Blake2b;
+
+use std::io::{
+    self,
+    Read,
+    Write
+};
+
+use std::ptr;
+use std::sync::atomic;
+
+use error::Error;
+
+use jubjub::{
+    edwards,
+    JubjubEngine,
+    PrimeOrder,
+    Unknown
+};
+
+/// Interprets a little endian byte string of arbitrary length as an
+/// integer and reduces it modulo the characteristic of the field.
+pub fn reduce_le_bytes<F: PrimeField>(bytes: &[u8]) -> F {
+    // 2^64 in the field
+    let mut base = F::from_repr(F::Repr::from(1 << 32)).expect("2^32 is in the field");
+    base.square();
+
+    let mut acc = F::zero();
+
+    // Horner's rule over 64-bit limbs, most significant first
+    for chunk in bytes.chunks(8).rev() {
+        let mut limb = 0u64;
+        for b in chunk.iter().rev() {
+            limb = (limb << 8) | (*b as u64);
+        }
+
+        acc.mul_assign(&base);
+        acc.add_assign(&F::from_repr(F::Repr::from(limb)).expect("limb is in the field"));
+    }
+
+    acc
+}
+
+/// Hashes the concatenation of `a` and `b` with BLAKE2b-512 under the
+/// given personalization, and reduces the digest to a Jubjub scalar.
+pub fn hash_to_scalar<E: JubjubEngine>(persona: &[u8], a: &[u8], b: &[u8]) -> E::Fs {
+    let mut h = Blake2b::with_params(64, &[], &[], persona);
+    h.update(a);
+    h.update(b);
+    let h = h.finalize();
+
+    reduce_le_bytes::<E::Fs>(h.as_ref())
+}
+
+/// Reads a canonical little endian encoding of a field element.
+pub fn read_scalar<F: PrimeField, R: Read>(mut reader: R) -> Result<F, Error> {
+    let mut repr = F::Repr::default();
+
+    let mut bytes = vec![0u8; repr.as_ref().len() * 8];
+    reader.read_exact(&mut bytes)?;
+    bytes.reverse();
+
+    repr.read_be(&bytes[..])?;
+
+    F::from_repr(repr).map_err(|_| Error::NonCanonicalEncoding)
+}
+
+/// Writes the little endian encoding of a field element.
+pub fn write_scalar<F: PrimeField, W: Write>(s: &F, mut writer: W) -> io::Result<()> {
+    let repr = s.into_repr();
+
+    let mut bytes = vec![0u8; repr.as_ref().len() * 8];
+    repr.write_be(&mut bytes[..])?;
+    bytes.reverse();
+
+    writer.write_all(&bytes)
+}
+
+/// Reads the encoding of a point in the prime order subgroup.
+pub fn read_prime_order_point<E: JubjubEngine, R: Read>(
+    reader: R,
+    params: &E::Params
+) -> Result<edwards::Point<E, PrimeOrder>, Error>
+{
+    edwards::Point::<E, Unknown>::read(reader, params)?
+        .as_prime_order(params)
+        .ok_or(Error::SmallOrderPoint)
+}
+
+/// Overwrites `x` with `zero` in a way that the compiler will not
+/// optimize away, so that secrets do not outlive the values holding
+/// them.
+pub fn zeroize<T: Copy>(x: &mut T, zero: T) {
+    unsafe {
+        ptr::write_volatile(x, zero);
+    }
+    atomic::compiler_fence(atomic::Ordering::SeqCst);
+}
+
+/// Overwrites `bytes` with zeroes, as `zeroize` does.
+pub fn zeroize_bytes(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        zeroize(b, 0);
+    }
+}
+
+/// A 64-byte buffer for hash preimages containing key material,
+/// which is zeroized when it is dropped. Only this buffer is cleared:
+/// the `blake2_rfc` state that it is hashed with keeps a copy of the
+/// last block, which cannot be zeroized from outside that crate.
+pub struct SecretBuffer(pub [u8; 64]);
+
+impl SecretBuffer {
+    pub fn new() -> Self {
+        SecretBuffer([0; 64])
+    }
+}
+
+impl Drop for SecretBuffer {
+    fn drop(&mut self) {
+        zeroize_bytes(&mut self.0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::{Field, PrimeField};
+    use jubjub::fs::Fs;
+    use super::*;
+
+    #[test]
+    fn test_reduce_le_bytes() {
+        // Small integers are unaffected.
+        let mut bytes = [0u8; 64];
+        bytes[0] = 0x39;
+        bytes[1] = 0x05;
+        assert_eq!(reduce_le_bytes::<Fs>(&bytes), Fs::from_str("1337").unwrap());
+
+        // The modulus reduces to zero.
+        let mut modulus = vec![];
+        for limb in Fs::char().as_ref() {
+            for i in 0..8 {
+                modulus.push((limb >> (i * 8)) as u8);
+            }
+        }
+        assert!(reduce_le_bytes::<Fs>(&modulus).is_zero());
+
+        // 2^256 reduces the same as its expansion in the field.
+        let mut bytes = [0u8; 33];
+        bytes[32] = 1;
+        let mut expected = Fs::from_str("2").unwrap().pow(&[256u64]);
+        expected.sub_assign(&reduce_le_bytes::<Fs>(&bytes));
+        assert!(expected.is_zero());
+    }
+
+    #[test]
+    fn test_scalar_read_write() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        for _ in 0..100 {
+            let s: Fs = rng.gen();
+
+            let mut bytes = vec![];
+            write_scalar::<Fs, _>(&s, &mut bytes).unwrap();
+            assert_eq!(bytes.len(), 32);
+            assert_eq!(read_scalar::<Fs, _>(&bytes[..]).unwrap(), s);
+
+            // Little endian encoding agrees with the reduction.
+            assert_eq!(reduce_le_bytes::<Fs>(&bytes), s);
+        }
+
+        // Non-canonical encodings are rejected.
+        assert!(read_scalar::<Fs, _>(&[0xff; 32][..]).is_err());
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let mut s: Fs = rng.gen();
+        zeroize(&mut s, Fs::zero());
+        assert!(s.is_zero());
+
+        let mut buf = SecretBuffer::new();
+        for b in buf.0.iter_mut() {
+            *b = rng.gen();
+        }
+        zeroize_bytes(&mut buf.0);
+        assert!(buf.0.iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,601 @@
+//! Verification of Groth16 proofs for the `Spend` and `Output`
+//! circuits against native public values.
+//!
+//! Proofs can be verified one at a time, or queued in a
+//! `BatchVerifier` which checks a random linear combination of all
+//! the verification equations across threads with a single final
+//! exponentiation.
+
+use crossbeam;
+use num_cpus;
+
+use pairing::{
+    CurveAffine,
+    CurveProjective,
+    Engine,
+    Field,
+    PrimeField
+};
+
+use bellman::groth16::{
+    prepare_verifying_key,
+    verify_proof,
+    PreparedVerifyingKey,
+    Proof,
+    VerifyingKey
+};
+
+use rand::Rng;
+
+use jubjub::{
+    edwards,
+    JubjubEngine,
+    Unknown
+};
+
+use public_inputs::{
+    OutputPublicInputs,
+    SpendPublicInputs
+};
+
+/// Returns true if `p` is of small order, in which case it
+/// cannot be a value commitment or ephemeral key.
+fn is_small_order<E: JubjubEngine>(
+    p: &edwards::Point<E, Unknown>,
+    params: &E::Params
+) -> bool
+{
+    p.mul_by_cofactor(params) == edwards::Point::zero()
+}
+
+/// Verifies a `Spend` proof for the given value commitment,
+/// anchor and nullifier.
+pub fn verify_spend_proof<E: JubjubEngine>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &SpendPublicInputs<E>,
+    params: &E::Params
+) -> bool
+{
+    if is_small_order(&public_inputs.cv, params) {
+        return false;
+    }
+
+    match verify_proof(pvk, proof, &public_inputs.to_field_elements()) {
+        Ok(valid) => valid,
+        Err(_) => false
+    }
+}
+
+/// Verifies an `Output` proof for the given value commitment,
+/// ephemeral public key and note commitment.
+pub fn verify_output_proof<E: JubjubEngine>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    public_inputs: &OutputPublicInputs<E>,
+    params: &E::Params
+) -> bool
+{
+    if is_small_order(&public_inputs.cv, params) || is_small_order(&public_inputs.epk, params) {
+        return false;
+    }
+
+    match verify_proof(pvk, proof, &public_inputs.to_field_elements()) {
+        Ok(valid) => valid,
+        Err(_) => false
+    }
+}
+
+/// Identifies the first invalid proof in a batch, by the order it
+/// was queued in among proofs for the same circuit.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvalidProof {
+    Spend(usize),
+    Output(usize)
+}
+
+/// Accumulates `Spend` and `Output` proofs with their public inputs
+/// so that they can be verified together.
+pub struct BatchVerifier<E: JubjubEngine> {
+    spends: Vec<(Proof<E>, SpendPublicInputs<E>)>,
+    outputs: Vec<(Proof<E>, OutputPublicInputs<E>)>
+}
+
+impl<E: JubjubEngine> BatchVerifier<E> {
+    pub fn new() -> Self {
+        BatchVerifier {
+            spends: vec![],
+            outputs: vec![]
+        }
+    }
+
+    /// Queues a `Spend` proof for verification.
+    pub fn queue_spend(
+        &mut self,
+        proof: Proof<E>,
+        public_inputs: SpendPublicInputs<E>
+    )
+    {
+        self.spends.push((proof, public_inputs));
+    }
+
+    /// Queues an `Output` proof for verification.
+    pub fn queue_output(
+        &mut self,
+        proof: Proof<E>,
+        public_inputs: OutputPublicInputs<E>
+    )
+    {
+        self.outputs.push((proof, public_inputs));
+    }
+
+    /// Verifies all of the queued proofs. If the batch does not
+    /// verify, the proofs are checked one at a time to find the
+    /// first invalid one; invalid `Spend` proofs are reported before
+    /// invalid `Output` proofs.
+    pub fn verify<R: Rng>(
+        &self,
+        spend_vk: &VerifyingKey<E>,
+        output_vk: &VerifyingKey<E>,
+        params: &E::Params,
+        rng: &mut R
+    ) -> Result<(), InvalidProof>
+    {
+        if self.verify_batch(spend_vk, output_vk, params, rng) {
+            return Ok(());
+        }
+
+        if !self.spends.is_empty() {
+            let pvk = prepare_verifying_key(spend_vk);
+
+            for (i, &(ref proof, ref public_inputs)) in self.spends.iter().enumerate() {
+                if !verify_spend_proof(&pvk, proof, public_inputs, params) {
+                    return Err(InvalidProof::Spend(i));
+                }
+            }
+        }
+
+        if !self.outputs.is_empty() {
+            let pvk = prepare_verifying_key(output_vk);
+
+            for (i, &(ref proof, ref public_inputs)) in self.outputs.iter().enumerate() {
+                if !verify_output_proof(&pvk, proof, public_inputs, params) {
+                    return Err(InvalidProof::Output(i));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_batch<R: Rng>(
+        &self,
+        spend_vk: &VerifyingKey<E>,
+        output_vk: &VerifyingKey<E>,
+        params: &E::Params,
+        rng: &mut R
+    ) -> bool
+    {
+        let mut spends: Vec<BatchItem<E>> = Vec::with_capacity(self.spends.len());
+        for &(ref proof, ref public_inputs) in &self.spends {
+            if is_small_order(&public_inputs.cv, params) {
+                return false;
+            }
+
+            spends.push((proof, public_inputs.to_field_elements(), rng.gen()));
+        }
+
+        let mut outputs: Vec<BatchItem<E>> = Vec::with_capacity(self.outputs.len());
+        for &(ref proof, ref public_inputs) in &self.outputs {
+            if is_small_order(&public_inputs.cv, params) || is_small_order(&public_inputs.epk, params) {
+                return false;
+            }
+
+            outputs.push((proof, public_inputs.to_field_elements(), rng.gen()));
+        }
+
+        let mut acc = E::Fqk::one();
+
+        for &(vk, ref items) in &[(spend_vk, spends), (output_vk, outputs)] {
+            if items.is_empty() {
+                continue;
+            }
+
+            if items.iter().any(|&(_, ref inputs, _)| inputs.len() + 1 != vk.ic.len()) {
+                return false;
+            }
+
+            acc.mul_assign(&combine(vk, &accumulate_parallel(items, vk.ic.len())));
+        }
+
+        E::final_exponentiation(&acc) == Some(E::Fqk::one())
+    }
+}
+
+/// A proof, its public inputs and the random scalar it is weighted
+/// by in the batch.
+type BatchItem<'a, E> = (&'a Proof<E>, Vec<<E as Engine>::Fr>, <E as Engine>::Fr);
+
+/// The random linear combination of the verification equations
+/// e(A, B) = e(alpha, beta) e(sum x_j IC_j, gamma) e(C, delta)
+/// for a set of proofs of the same circuit.
+struct Accumulator<E: Engine> {
+    /// The product of the Miller loops of (r A, B)
+    miller: E::Fqk,
+    /// The sum of r C
+    c: E::G1,
+    /// The coefficients of IC, where the first is the sum of r
+    ic: Vec<E::Fr>
+}
+
+impl<E: Engine> Accumulator<E> {
+    fn new(ic_len: usize) -> Self {
+        Accumulator {
+            miller: E::Fqk::one(),
+            c: E::G1::zero(),
+            ic: vec![E::Fr::zero(); ic_len]
+        }
+    }
+
+    fn add_assign(&mut self, other: &Self) {
+        self.miller.mul_assign(&other.miller);
+        self.c.add_assign(&other.c);
+
+        for (a, b) in self.ic.iter_mut().zip(other.ic.iter()) {
+            a.add_assign(b);
+        }
+    }
+}
+
+fn accumulate<E: Engine>(
+    items: &[BatchItem<E>],
+    ic_len: usize
+) -> Accumulator<E>
+{
+    let mut acc = Accumulator::new(ic_len);
+    let mut a = Vec::with_capacity(items.len());
+    let mut b = Vec::with_capacity(items.len());
+
+    for &(proof, ref inputs, r) in items {
+        a.push(proof.a.mul(r.into_repr()).into_affine().prepare());
+        b.push(proof.b.prepare());
+        acc.c.add_assign(&proof.c.mul(r.into_repr()));
+
+        acc.ic[0].add_assign(&r);
+        for (coeff, x) in acc.ic[1..].iter_mut().zip(inputs.iter()) {
+            let mut tmp = *x;
+            tmp.mul_assign(&r);
+            coeff.add_assign(&tmp);
+        }
+    }
+
+    let pairs: Vec<_> = a.iter().zip(b.iter()).collect();
+    acc.miller = E::miller_loop(&pairs);
+
+    acc
+}
+
+/// Accumulates the items in chunks, one per CPU.
+fn accumulate_parallel<E: Engine>(
+    items: &[BatchItem<E>],
+    ic_len: usize
+) -> Accumulator<E>
+{
+    let cpus = num_cpus::get();
+    let chunk_size = (items.len() + cpus - 1) / cpus;
+
+    let accs = crossbeam::scope(|scope| {
+        let handles: Vec<_> = items.chunks(chunk_size).map(|chunk| {
+            scope.spawn(move || accumulate(chunk, ic_len))
+        }).collect();
+
+        handles.into_iter().map(|h| h.join()).collect::<Vec<_>>()
+    });
+
+    let mut acc = Accumulator::new(ic_len);
+    for other in &accs {
+        acc.add_assign(other);
+    }
+
+    acc
+}
+
+/// Computes the Miller loop of the combined verification equation,
+/// which is one after the final exponentiation if the proofs are
+/// valid.
+fn combine<E: Engine>(
+    vk: &VerifyingKey<E>,
+    acc: &Accumulator<E>
+) -> E::Fqk
+{
+    let mut alpha = vk.alpha_g1.mul(acc.ic[0].into_repr());
+    alpha.negate();
+
+    let mut ic = E::G1::zero();
+    for (base, coeff) in vk.ic.iter().zip(acc.ic.iter()) {
+        ic.add_assign(&base.mul(coeff.into_repr()));
+    }
+
+    let mut neg_gamma = vk.gamma_g2;
+    neg_gamma.negate();
+    let mut neg_delta = vk.delta_g2;
+    neg_delta.negate();
+
+    let mut miller = E::miller_loop(&[
+        (&alpha.into_affine().prepare(), &vk.beta_g2.prepare()),
+        (&ic.into_affine().prepare(), &neg_gamma.prepare()),
+        (&acc.c.into_affine().prepare(), &neg_delta.prepare())
+    ]);
+    miller.mul_assign(&acc.miller);
+
+    miller
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Rng, XorShiftRng};
+    use pairing::Field;
+    use pairing::bls12_381::{Bls12, Fr};
+    use bellman::groth16::prepare_verifying_key;
+    use jubjub::{JubjubBls12, fs, edwards};
+    use keys::{ExpandedSpendingKey, FullViewingKey};
+    use merkle_tree::{CommitmentTree, IncrementalWitness};
+    use primitives::ValueCommitment;
+    use prover::*;
+    use super::*;
+
+    #[test]
+    fn test_output_proof() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let proving_key = output_parameters::<Bls12, _>(params, rng).unwrap();
+        let pvk = prepare_verifying_key(&proving_key.vk);
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[0u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&expsk, params);
+        let (_, to) = fvk.default_address(params).unwrap();
+
+        let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+        let esk: fs::Fs = rng.gen();
+        let rcv: fs::Fs = rng.gen();
+
+        let (proof, public_inputs) = create_output_proof(
+            &note, to.diversifier, esk, rcv, &proving_key, params, rng
+        ).unwrap();
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: note.value,
+            randomness: rcv
+        };
+        let expected = OutputPublicInputs::from_note(&value_commitment, &note, esk, params);
+        assert_eq!(public_inputs.to_field_elements(), expected.to_field_elements());
+        assert!(verify_output_proof(&pvk, &proof, &expected, params));
+
+        // The proof does not verify for other public inputs.
+        let mut cm = expected.cm;
+        cm.add_assign(&Fr::one());
+        let other = OutputPublicInputs::new(expected.cv.clone(), expected.epk.clone(), cm);
+        assert!(!verify_output_proof(&pvk, &proof, &other, params));
+        let other = OutputPublicInputs::new(expected.epk.clone(), expected.cv.clone(), expected.cm);
+        assert!(!verify_output_proof(&pvk, &proof, &other, params));
+        let other = OutputPublicInputs::new(edwards::Point::zero(), expected.epk.clone(), expected.cm);
+        assert!(!verify_output_proof(&pvk, &proof, &other, params));
+
+        // The diversifier must match the note.
+        let mut d = to.diversifier;
+        d.0[0] ^= 1;
+        assert!(create_output_proof(&note, d, esk, rcv, &proving_key, params, rng).is_err());
+    }
+
+    #[test]
+    fn test_batch_verification() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let proving_key = output_parameters::<Bls12, _>(params, rng).unwrap();
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[0u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&expsk, params);
+        let (_, to) = fvk.default_address(params).unwrap();
+
+        let mut proofs = vec![];
+        for _ in 0..5 {
+            let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+            proofs.push(create_output_proof(
+                &note, to.diversifier, rng.gen(), rng.gen(), &proving_key, params, rng
+            ).unwrap());
+        }
+
+        // No Spend proofs are queued, so the Output verifying key
+        // stands in for the Spend one.
+        let vk = &proving_key.vk;
+
+        let mut batch = BatchVerifier::new();
+        assert_eq!(batch.verify(vk, vk, params, rng), Ok(()));
+
+        for &(ref proof, ref public_inputs) in &proofs {
+            batch.queue_output(Proof { a: proof.a, b: proof.b, c: proof.c }, OutputPublicInputs::new(
+                public_inputs.cv.clone(), public_inputs.epk.clone(), public_inputs.cm
+            ));
+        }
+        assert_eq!(batch.verify(vk, vk, params, rng), Ok(()));
+
+        // A proof with the wrong note commitment is identified.
+        let mut cm = proofs[2].1.cm;
+        cm.add_assign(&Fr::one());
+        let mut batch = BatchVerifier::new();
+        for (i, &(ref proof, ref public_inputs)) in proofs.iter().enumerate() {
+            batch.queue_output(Proof { a: proof.a, b: proof.b, c: proof.c }, OutputPublicInputs::new(
+                public_inputs.cv.clone(), public_inputs.epk.clone(), if i == 2 { cm } else { public_inputs.cm }
+            ));
+        }
+        assert_eq!(batch.verify(vk, vk, params, rng), Err(InvalidProof::Output(2)));
+
+        // So is one with a small order value commitment.
+        let mut batch = BatchVerifier::new();
+        for (i, &(ref proof, ref public_inputs)) in proofs.iter().enumerate() {
+            let cv = if i == 4 { edwards::Point::zero() } else { public_inputs.cv.clone() };
+            batch.queue_output(Proof { a: proof.a, b: proof.b, c: proof.c }, OutputPublicInputs::new(
+                cv, public_inputs.epk.clone(), public_inputs.cm
+            ));
+        }
+        assert_eq!(batch.verify(vk, vk, params, rng), Err(InvalidProof::Output(4)));
+    }
+
+    // Generating parameters for the full Spend circuit is slow.
+    #[test]
+    #[ignore]
+    fn test_spend_proof() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let proving_key = spend_parameters::<Bls12, _>(params, rng).unwrap();
+        let pvk = prepare_verifying_key(&proving_key.vk);
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[0u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&expsk, params);
+        let (_, to) = fvk.default_address(params).unwrap();
+        let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        tree.append(rng.gen(), params).unwrap();
+        tree.append(note.cm(params), params).unwrap();
+        let mut witness = IncrementalWitness::from_tree(&tree);
+        witness.append(rng.gen(), params).unwrap();
+        let path = witness.path(params).unwrap();
+
+        let rcv: fs::Fs = rng.gen();
+        let (proof, public_inputs) = create_spend_proof(
+            &note, to.diversifier, &expsk.proof_generation_key(params), &path, rcv, &proving_key, params, rng
+        ).unwrap();
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: note.value,
+            randomness: rcv
+        };
+        let expected = SpendPublicInputs::from_note(&value_commitment, &note, &fvk.vk, &path, params);
+        assert!(expected.anchor == witness.root(params));
+        assert_eq!(public_inputs.to_field_elements(), expected.to_field_elements());
+        assert!(verify_spend_proof(&pvk, &proof, &expected, params));
+
+        let mut anchor = expected.anchor;
+        anchor.add_assign(&Fr::one());
+        let other = SpendPublicInputs::new(expected.cv.clone(), anchor, expected.nf.clone());
+        assert!(!verify_spend_proof(&pvk, &proof, &other, params));
+    }
+
+    // Generating parameters for the full Spend circuit is slow.
+    #[test]
+    #[ignore]
+    fn test_batch_verification_with_spends() {
+        let params = &JubjubBls12::new();
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let spend_key = spend_parameters::<Bls12, _>(params, rng).unwrap();
+        let output_key = output_parameters::<Bls12, _>(params, rng).unwrap();
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[0u8; 32]);
+        let fvk = FullViewingKey::from_expanded_spending_key(&expsk, params);
+        let pgk = expsk.proof_generation_key(params);
+        let (_, to) = fvk.default_address(params).unwrap();
+
+        let notes: Vec<_> = (0..3).map(|_| to.create_note(rng.gen(), rng.gen(), params).unwrap()).collect();
+
+        let mut tree = CommitmentTree::<Bls12>::new();
+        let mut witnesses: Vec<IncrementalWitness<Bls12>> = vec![];
+        for note in &notes {
+            tree.append(note.cm(params), params).unwrap();
+            for witness in &mut witnesses {
+                witness.append(note.cm(params), params).unwrap();
+            }
+            witnesses.push(IncrementalWitness::from_tree(&tree));
+        }
+
+        let mut spends = vec![];
+        for (note, witness) in notes.iter().zip(witnesses.iter()) {
+            let path = witness.path(params).unwrap();
+            spends.push(create_spend_proof(
+                note, to.diversifier, &pgk, &path, rng.gen(), &spend_key, params, rng
+            ).unwrap());
+        }
+
+        let mut outputs = vec![];
+        for _ in 0..2 {
+            let note = to.create_note(rng.gen(), rng.gen(), params).unwrap();
+            outputs.push(create_output_proof(
+                &note, to.diversifier, rng.gen(), rng.gen(), &output_key, params, rng
+            ).unwrap());
+        }
+
+        let spend_inputs = || -> Vec<SpendPublicInputs<Bls12>> {
+            spends.iter().map(|&(_, ref p)| {
+                SpendPublicInputs::new(p.cv.clone(), p.anchor, p.nf.clone())
+            }).collect()
+        };
+        let output_inputs = || -> Vec<OutputPublicInputs<Bls12>> {
+            outputs.iter().map(|&(_, ref p)| {
+                OutputPublicInputs::new(p.cv.clone(), p.epk.clone(), p.cm)
+            }).collect()
+        };
+
+        // Queues every proof with the given public inputs.
+        let batch = |spend_inputs: Vec<SpendPublicInputs<Bls12>>, output_inputs: Vec<OutputPublicInputs<Bls12>>| {
+            let mut batch = BatchVerifier::new();
+            for (&(ref proof, _), public_inputs) in spends.iter().zip(spend_inputs) {
+                batch.queue_spend(Proof { a: proof.a, b: proof.b, c: proof.c }, public_inputs);
+            }
+            for (&(ref proof, _), public_inputs) in outputs.iter().zip(output_inputs) {
+                batch.queue_output(Proof { a: proof.a, b: proof.b, c: proof.c }, public_inputs);
+            }
+
+            batch
+        };
+
+        let spend_vk = &spend_key.vk;
+        let output_vk = &output_key.vk;
+
+        // All of the proofs share the same anchor.
+        assert!(spend_inputs().iter().all(|p| p.anchor == tree.root(params)));
+
+        let valid = batch(spend_inputs(), output_inputs());
+        assert_eq!(valid.verify(spend_vk, output_vk, params, rng), Ok(()));
+
+        // A Spend proof with the wrong anchor is identified.
+        let mut inputs = spend_inputs();
+        inputs[1].anchor.add_assign(&Fr::one());
+        assert_eq!(
+            batch(inputs, output_inputs()).verify(spend_vk, output_vk, params, rng),
+            Err(InvalidProof::Spend(1))
+        );
+
+        // So is one with a small order value commitment.
+        let mut inputs = spend_inputs();
+        inputs[2].cv = edwards::Point::zero();
+        assert_eq!(
+            batch(inputs, output_inputs()).verify(spend_vk, output_vk, params, rng),
+            Err(InvalidProof::Spend(2))
+        );
+
+        // Invalid Spend proofs are reported before invalid Output proofs.
+        let mut inputs = spend_inputs();
+        inputs[0].cv = edwards::Point::zero();
+        let mut other_inputs = output_inputs();
+        other_inputs[1].cm.add_assign(&Fr::one());
+        assert_eq!(
+            batch(inputs, other_inputs).verify(spend_vk, output_vk, params, rng),
+            Err(InvalidProof::Spend(0))
+        );
+
+        // An invalid Output proof alongside valid Spend proofs.
+        let mut other_inputs = output_inputs();
+        other_inputs[1].cm.add_assign(&Fr::one());
+        assert_eq!(
+            batch(spend_inputs(), other_inputs).verify(spend_vk, output_vk, params, rng),
+            Err(InvalidProof::Output(1))
+        );
+
+        // Swapping the verifying keys fails every proof.
+        assert_eq!(valid.verify(output_vk, spend_vk, params, rng), Err(InvalidProof::Spend(0)));
+    }
+}